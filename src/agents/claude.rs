@@ -3,12 +3,22 @@
 //! This adapter integrates with Claude Code CLI (`claude --print`).
 
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use notify::{RecursiveMode, Watcher};
+use std::ffi::OsString;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
 
-use crate::parser::{parse_jsonl_file, ToolCall};
+use crate::parser::{parse_jsonl_file_with_subagents, ParseMode, ParsedSession};
+use crate::stream::SessionTail;
+use super::env::apply_env;
 use super::mapping::{canonical, ToolNameMapping};
-use super::traits::{Agent, ExecutionConfig, RawExecutionResult};
+use super::traits::{Agent, AgentOptions, ExecutionConfig, RawExecutionResult, ToolCallObserver};
+
+/// How often to check the session log when no file system event arrives.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
 
 /// Claude Code agent adapter.
 pub struct ClaudeAdapter {
@@ -25,6 +35,7 @@ impl ClaudeAdapter {
             .add("Read", canonical::READ)
             .add("Write", canonical::WRITE)
             .add("Edit", canonical::EDIT)
+            .add("MultiEdit", canonical::MULTI_EDIT)
             .add("Bash", canonical::BASH)
             .add("Grep", canonical::GREP)
             .add("Glob", canonical::GLOB)
@@ -36,7 +47,11 @@ impl ClaudeAdapter {
             .add("NotebookEdit", canonical::NOTEBOOK_EDIT)
             .add("TodoWrite", canonical::TODO_WRITE)
             .add("KillShell", canonical::KILL_SHELL)
-            .add("TaskOutput", canonical::TASK_OUTPUT);
+            .add("TaskOutput", canonical::TASK_OUTPUT)
+            .add("Skill", canonical::SKILL)
+            .add("SlashCommand", canonical::SLASH_COMMAND)
+            .add("ExitPlanMode", canonical::EXIT_PLAN_MODE)
+            .add("BashOutput", canonical::BASH_OUTPUT);
 
         Self { mapping }
     }
@@ -67,6 +82,12 @@ impl Agent for ClaudeAdapter {
         let mut cmd = Command::new("claude");
         cmd.arg("--print").arg(prompt).stdin(Stdio::null());
 
+        if let Some(session_id) = &config.resume {
+            cmd.arg("--resume").arg(session_id);
+        }
+        cmd.args(option_args(&config.options));
+        apply_env(&mut cmd, &config.options)?;
+
         if let Some(dir) = &config.working_dir {
             cmd.current_dir(dir);
         }
@@ -75,14 +96,20 @@ impl Agent for ClaudeAdapter {
             cmd.arg(arg);
         }
 
-        let output = cmd.output().context("Failed to execute claude command")?;
+        let resume = config.resume.as_deref();
+        let stdout = match &config.on_tool_call {
+            Some(observer) => {
+                run_observed(cmd, &project_dir, &existing_sessions, resume, observer)?
+            }
+            None => cmd.output().context("Failed to execute claude command")?.stdout,
+        };
 
         // Capture stdout
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stdout = String::from_utf8_lossy(&stdout).to_string();
         let stdout = if stdout.is_empty() { None } else { Some(stdout) };
 
         // Find the new session log file (only in this project)
-        let session_log_path = find_new_session(&project_dir, &existing_sessions)?;
+        let session_log_path = find_new_session(&project_dir, &existing_sessions, resume)?;
 
         Ok(RawExecutionResult {
             session_log_path: Some(session_log_path),
@@ -90,12 +117,16 @@ impl Agent for ClaudeAdapter {
         })
     }
 
-    fn parse_session(&self, result: &RawExecutionResult) -> Result<Vec<ToolCall>> {
+    fn parse_session(
+        &self,
+        result: &RawExecutionResult,
+        mode: ParseMode,
+    ) -> Result<ParsedSession> {
         let path = result
             .session_log_path
             .as_ref()
             .context("Claude requires session log path")?;
-        parse_jsonl_file(path)
+        parse_jsonl_file_with_subagents(path, mode)
     }
 
     fn tool_mapping(&self) -> &ToolNameMapping {
@@ -113,6 +144,102 @@ impl Agent for ClaudeAdapter {
     }
 }
 
+/// Translate typed options into claude CLI flags.
+fn option_args(options: &AgentOptions) -> Vec<OsString> {
+    let mut args: Vec<OsString> = Vec::new();
+    let mut flag = |name: &str, value: OsString| {
+        args.push(name.into());
+        args.push(value);
+    };
+
+    if !options.allowed_tools.is_empty() {
+        flag("--allowedTools", options.allowed_tools.join(",").into());
+    }
+    if !options.disallowed_tools.is_empty() {
+        flag("--disallowedTools", options.disallowed_tools.join(",").into());
+    }
+    if let Some(mode) = &options.permission_mode {
+        flag("--permission-mode", mode.into());
+    }
+    if let Some(text) = &options.append_system_prompt {
+        flag("--append-system-prompt", text.into());
+    }
+    if let Some(path) = &options.mcp_config {
+        flag("--mcp-config", path.into());
+    }
+    if let Some(turns) = options.max_turns {
+        flag("--max-turns", turns.to_string().into());
+    }
+    args
+}
+
+/// Run claude while tailing its new session log, notifying the observer of each tool call.
+///
+/// A resumed session is tailed from the start, so the observer also sees the
+/// calls of earlier turns. Returns the captured stdout once the process exits.
+fn run_observed(
+    mut cmd: Command,
+    project_dir: &Path,
+    existing: &[PathBuf],
+    resume: Option<&str>,
+    observer: &ToolCallObserver,
+) -> Result<Vec<u8>> {
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to execute claude command")?;
+
+    // Drain stdout on a separate thread so a full pipe can't block the agent
+    let mut child_stdout = child.stdout.take().context("Failed to capture claude stdout")?;
+    let stdout_reader = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        child_stdout.read_to_end(&mut buf).map(|_| buf)
+    });
+
+    // File system events wake us early; polling covers platforms where watching fails
+    let (tx, rx) = mpsc::channel();
+    let _watcher = notify::recommended_watcher(tx).ok().and_then(|mut w| {
+        w.watch(project_dir, RecursiveMode::Recursive).ok().map(|_| w)
+    });
+
+    let mut tail: Option<SessionTail> = None;
+    loop {
+        let finished = child
+            .try_wait()
+            .context("Failed to wait for claude command")?
+            .is_some();
+
+        if tail.is_none() {
+            let current = list_session_files(project_dir)?;
+            // Only a new file is surely this run's log while the agent runs; the
+            // resumed log is the fallback once it exits, as in `find_new_session`
+            let resume = if finished { resume } else { None };
+            tail = pick_session(&current, existing, resume).map(SessionTail::new);
+        }
+
+        if let Some(tail) = tail.as_mut() {
+            let calls = if finished { tail.finish()? } else { tail.poll()? };
+            for call in &calls {
+                observer.notify(call);
+            }
+        }
+
+        if finished {
+            break;
+        }
+        if let Err(mpsc::RecvTimeoutError::Disconnected) = rx.recv_timeout(POLL_INTERVAL) {
+            // No watcher is running, so fall back to plain polling
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    stdout_reader
+        .join()
+        .map_err(|_| anyhow::anyhow!("claude stdout reader panicked"))?
+        .context("Failed to read claude stdout")
+}
+
 /// Get the Claude projects directory.
 fn get_claude_projects_dir() -> Result<PathBuf> {
     let home = dirs::home_dir().context("Could not find home directory")?;
@@ -133,7 +260,7 @@ fn get_claude_projects_dir() -> Result<PathBuf> {
 /// Claude Code stores sessions in directories named after the working directory path,
 /// with slashes replaced by dashes. E.g., /Users/foo/bar becomes -Users-foo-bar
 fn get_project_dir_for_workdir(
-    claude_dir: &PathBuf,
+    claude_dir: &Path,
     working_dir: &Option<PathBuf>,
 ) -> Result<PathBuf> {
     let workdir = match working_dir {
@@ -152,7 +279,7 @@ fn get_project_dir_for_workdir(
 
     // If the specific project dir doesn't exist, fall back to searching all projects
     if !project_dir.exists() {
-        return Ok(claude_dir.clone());
+        return Ok(claude_dir.to_path_buf());
     }
 
     Ok(project_dir)
@@ -160,7 +287,7 @@ fn get_project_dir_for_workdir(
 
 /// List all JSONL session files in the claude directory.
 /// Excludes subagent logs (files in /subagents/ directories).
-fn list_session_files(claude_dir: &PathBuf) -> Result<Vec<PathBuf>> {
+fn list_session_files(claude_dir: &Path) -> Result<Vec<PathBuf>> {
     let mut files = Vec::new();
 
     if claude_dir.exists() {
@@ -173,7 +300,7 @@ fn list_session_files(claude_dir: &PathBuf) -> Result<Vec<PathBuf>> {
             if path.to_string_lossy().contains("/subagents/") {
                 continue;
             }
-            if path.extension().map_or(false, |ext| ext == "jsonl") {
+            if path.extension().is_some_and(|ext| ext == "jsonl") {
                 files.push(path.to_path_buf());
             }
         }
@@ -182,15 +309,40 @@ fn list_session_files(claude_dir: &PathBuf) -> Result<Vec<PathBuf>> {
     Ok(files)
 }
 
+/// Check if a session log belongs to the session being resumed.
+///
+/// Session logs are named after the session id.
+fn is_session(path: &Path, session_id: Option<&str>) -> bool {
+    session_id.is_some_and(|id| path.file_stem().is_some_and(|stem| stem == id))
+}
+
+/// Pick the log of the session just run from the current session logs.
+///
+/// A file that wasn't in the existing list wins. When resuming, the resumed
+/// session's log is used if no new file appears.
+fn pick_session<'a>(
+    current: &'a [PathBuf],
+    existing: &[PathBuf],
+    resume: Option<&str>,
+) -> Option<&'a PathBuf> {
+    current
+        .iter()
+        .find(|path| !existing.contains(path))
+        .or_else(|| current.iter().find(|path| is_session(path, resume)))
+}
+
 /// Find a new session log file that wasn't in the existing list.
-fn find_new_session(claude_dir: &PathBuf, existing: &[PathBuf]) -> Result<PathBuf> {
+///
+/// When resuming, the resumed session's log is used if no new file appears.
+fn find_new_session(
+    claude_dir: &Path,
+    existing: &[PathBuf],
+    resume: Option<&str>,
+) -> Result<PathBuf> {
     let current = list_session_files(claude_dir)?;
 
-    // Find files that are new or modified
-    for path in &current {
-        if !existing.contains(path) {
-            return Ok(path.clone());
-        }
+    if let Some(path) = pick_session(&current, existing, resume) {
+        return Ok(path.clone());
     }
 
     // If no new file, find the most recently modified from the filtered list
@@ -214,3 +366,23 @@ fn find_new_session(claude_dir: &PathBuf, existing: &[PathBuf]) -> Result<PathBu
         .map(|(path, _)| path)
         .context("Could not find session log file")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_session_prefers_new_file() {
+        let resumed = PathBuf::from("/p/abc.jsonl");
+        let new = PathBuf::from("/p/def.jsonl");
+        let existing = vec![resumed.clone()];
+
+        // The resumed log is listed first, but a new file still wins
+        let current = vec![resumed.clone(), new.clone()];
+        assert_eq!(pick_session(&current, &existing, Some("abc")), Some(&new));
+
+        let current = vec![resumed.clone()];
+        assert_eq!(pick_session(&current, &existing, Some("abc")), Some(&resumed));
+        assert_eq!(pick_session(&current, &existing, None), None);
+    }
+}
@@ -0,0 +1,402 @@
+//! Environment control for agent processes.
+//!
+//! Agents inherit the parent environment unless [`AgentOptions::env_clear`]
+//! says otherwise. Variables from [`AgentOptions::env_file`] are set next,
+//! then [`AgentOptions::env`], which wins over the file. Variables named in
+//! [`AgentOptions::secrets`] can be masked in output with a [`Redactor`].
+
+use anyhow::{bail, Context, Result};
+use serde::de::value::SeqAccessDeserializer;
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+
+use super::traits::AgentOptions;
+use crate::parser::{ParseReport, ParseWarning, ToolCall};
+
+/// Text shown in place of a secret value.
+const REDACTED: &str = "[REDACTED]";
+
+/// Shortest start of a secret masked where a truncated snippet cuts it off.
+const MIN_PARTIAL_SECRET: usize = 4;
+
+/// Which inherited environment variables the agent process keeps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "yaml", derive(schemars::JsonSchema), serde(untagged))]
+pub enum EnvClear {
+    /// `true` clears every inherited variable; `false` keeps them all.
+    All(bool),
+    /// Clear every inherited variable except these.
+    Except(Vec<String>),
+}
+
+// Deserialized by hand rather than as an untagged enum, so a wrong value is
+// reported with its line and column instead of "did not match any variant".
+impl<'de> Deserialize<'de> for EnvClear {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EnvClearVisitor;
+
+        impl<'de> Visitor<'de> for EnvClearVisitor {
+            type Value = EnvClear;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("true, false or a list of variables to keep")
+            }
+
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<EnvClear, E> {
+                Ok(EnvClear::All(value))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<EnvClear, A::Error> {
+                Vec::deserialize(SeqAccessDeserializer::new(seq)).map(EnvClear::Except)
+            }
+        }
+
+        deserializer.deserialize_any(EnvClearVisitor)
+    }
+}
+
+impl AgentOptions {
+    /// Variables set on top of the inherited environment: `env_file`, then `env`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the env file cannot be read or has a malformed line.
+    pub fn env_vars(&self) -> Result<BTreeMap<String, String>> {
+        let mut vars = match &self.env_file {
+            Some(path) => load_env_file(path)?,
+            None => BTreeMap::new(),
+        };
+        vars.extend(self.env.iter().map(|(k, v)| (k.clone(), v.clone())));
+        Ok(vars)
+    }
+
+    /// Check if the agent process inherits a variable from this process.
+    pub fn inherits(&self, name: &str) -> bool {
+        match &self.env_clear {
+            None | Some(EnvClear::All(false)) => true,
+            Some(EnvClear::All(true)) => false,
+            Some(EnvClear::Except(keep)) => keep.iter().any(|k| k == name),
+        }
+    }
+
+    /// A redactor for the values the agent sees for `secrets`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the env file cannot be loaded.
+    pub fn redactor(&self) -> Result<Redactor> {
+        let vars = self.env_vars()?;
+        let values = self.secrets.iter().filter_map(|name| {
+            vars.get(name)
+                .cloned()
+                .or_else(|| self.inherits(name).then(|| std::env::var(name).ok())?)
+        });
+        Ok(Redactor::new(values))
+    }
+}
+
+/// Set up the agent command's environment from the options.
+pub(crate) fn apply_env(cmd: &mut Command, options: &AgentOptions) -> Result<()> {
+    match &options.env_clear {
+        Some(EnvClear::All(true)) => {
+            cmd.env_clear();
+        }
+        Some(EnvClear::Except(keep)) => {
+            cmd.env_clear();
+            for name in keep {
+                if let Some(value) = std::env::var_os(name) {
+                    cmd.env(name, value);
+                }
+            }
+        }
+        None | Some(EnvClear::All(false)) => {}
+    }
+    cmd.envs(options.env_vars()?);
+    Ok(())
+}
+
+/// Load a dotenv-style file of `KEY=VALUE` lines.
+///
+/// Blank lines and `#` comments are skipped, and an `export ` prefix is
+/// allowed. Values may be single- or double-quoted; double-quoted values
+/// support `\n`, `\"` and `\\` escapes.
+pub fn load_env_file(path: &Path) -> Result<BTreeMap<String, String>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read env file: {:?}", path))?;
+    parse_env(&contents).with_context(|| format!("Invalid env file: {:?}", path))
+}
+
+fn parse_env(contents: &str) -> Result<BTreeMap<String, String>> {
+    let mut vars = BTreeMap::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("line {}: expected KEY=VALUE", index + 1);
+        };
+        let key = key.trim();
+        let valid = key
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            bail!("line {}: invalid variable name '{}'", index + 1, key);
+        }
+        vars.insert(key.to_string(), parse_value(value.trim()));
+    }
+    Ok(vars)
+}
+
+fn parse_value(value: &str) -> String {
+    if let Some(inner) = quoted(value, '\'') {
+        return inner.to_string();
+    }
+    if let Some(inner) = quoted(value, '"') {
+        let mut out = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            match (c, chars.clone().next()) {
+                ('\\', Some('n')) => out.push('\n'),
+                ('\\', Some(escaped @ ('"' | '\\'))) => out.push(escaped),
+                _ => {
+                    out.push(c);
+                    continue;
+                }
+            }
+            chars.next();
+        }
+        return out;
+    }
+    // Unquoted values end at an inline comment
+    match value.find(" #") {
+        Some(end) => value[..end].trim_end().to_string(),
+        None => value.to_string(),
+    }
+}
+
+fn quoted(value: &str, quote: char) -> Option<&str> {
+    value.strip_prefix(quote)?.strip_suffix(quote)
+}
+
+/// Masks secret values in text shown to the user.
+///
+/// Assertions see the real values; only output is redacted, so a test can
+/// still check that a honeypot key never leaks.
+///
+/// # Example
+///
+/// ```rust
+/// use aptitude::agents::Redactor;
+///
+/// let redactor = Redactor::new(["sk_test_honeypot".to_string()]);
+/// assert_eq!(redactor.redact("key=sk_test_honeypot"), "key=[REDACTED]");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    /// A redactor for these values. Empty values are ignored.
+    pub fn new(values: impl IntoIterator<Item = String>) -> Self {
+        let mut secrets: Vec<String> = values.into_iter().filter(|v| !v.is_empty()).collect();
+        // Longest first, so a secret containing another is masked whole
+        secrets.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        secrets.dedup();
+        Self { secrets }
+    }
+
+    /// Check if there is nothing to redact.
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty()
+    }
+
+    /// Replace every secret value in the text.
+    pub fn redact(&self, text: &str) -> String {
+        self.secrets.iter().fold(text.to_string(), |text, secret| {
+            text.replace(secret, REDACTED)
+        })
+    }
+
+    /// A copy of the tool call with secret values replaced in every string parameter.
+    pub fn redact_call(&self, call: &ToolCall) -> ToolCall {
+        ToolCall {
+            params: self.redact_value(&call.params),
+            ..call.clone()
+        }
+    }
+
+    /// [`redact_call`](Self::redact_call) for each call.
+    pub fn redact_calls(&self, calls: &[ToolCall]) -> Vec<ToolCall> {
+        calls.iter().map(|call| self.redact_call(call)).collect()
+    }
+
+    /// A copy of the parse report with secret values replaced in each warning.
+    ///
+    /// Warning snippets are cut off at a fixed length, so a secret split by
+    /// the cut is masked too.
+    pub fn redact_report(&self, report: &ParseReport) -> ParseReport {
+        let warnings = report
+            .warnings
+            .iter()
+            .map(|warning| ParseWarning {
+                snippet: self.redact_snippet(&warning.snippet),
+                error: self.redact(&warning.error),
+                ..warning.clone()
+            })
+            .collect();
+        ParseReport {
+            warnings,
+            ..report.clone()
+        }
+    }
+
+    fn redact_snippet(&self, snippet: &str) -> String {
+        let redacted = self.redact(snippet);
+        let Some(kept) = redacted.strip_suffix("...") else {
+            return redacted;
+        };
+        let partial = self
+            .secrets
+            .iter()
+            .flat_map(|secret| secret.char_indices().skip(1).map(|(i, _)| &secret[..i]))
+            .filter(|start| start.len() >= MIN_PARTIAL_SECRET && kept.ends_with(*start))
+            .map(str::len)
+            .max();
+        match partial {
+            Some(len) => format!("{}{}...", &kept[..kept.len() - len], REDACTED),
+            None => redacted,
+        }
+    }
+
+    fn redact_value(&self, value: &Value) -> Value {
+        match value {
+            Value::String(s) => Value::String(self.redact(s)),
+            Value::Array(items) => {
+                Value::Array(items.iter().map(|v| self.redact_value(v)).collect())
+            }
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), self.redact_value(v)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_env() {
+        let vars = parse_env(
+            "# Honeypot\n\
+             STRIPE_SECRET_KEY=sk_test_honeypot\n\
+             export API_URL = http://localhost:9999 # local\n\
+             GREETING=\"hello \\\"world\\\"\\nbye\"\n\
+             RAW='a # b'\n\
+             EMPTY=\n",
+        )
+        .unwrap();
+        assert_eq!(vars["STRIPE_SECRET_KEY"], "sk_test_honeypot");
+        assert_eq!(vars["API_URL"], "http://localhost:9999");
+        assert_eq!(vars["GREETING"], "hello \"world\"\nbye");
+        assert_eq!(vars["RAW"], "a # b");
+        assert_eq!(vars["EMPTY"], "");
+
+        let err = parse_env("OK=1\nnot a variable\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: expected KEY=VALUE");
+        assert!(parse_env("1BAD=x\n").is_err());
+    }
+
+    #[test]
+    fn test_env_vars_and_clear() {
+        let options = AgentOptions {
+            env: [("LEVEL".to_string(), "debug".to_string())].into(),
+            env_clear: Some(EnvClear::Except(vec!["PATH".to_string()])),
+            ..AgentOptions::default()
+        };
+        assert_eq!(options.env_vars().unwrap()["LEVEL"], "debug");
+        assert!(options.inherits("PATH"));
+        assert!(!options.inherits("HOME"));
+        assert!(AgentOptions::default().inherits("HOME"));
+    }
+
+    #[test]
+    fn test_redactor() {
+        let options = AgentOptions {
+            env: [
+                (
+                    "STRIPE_SECRET_KEY".to_string(),
+                    "sk_test_honeypot".to_string(),
+                ),
+                ("PUBLIC".to_string(), "visible".to_string()),
+            ]
+            .into(),
+            secrets: vec!["STRIPE_SECRET_KEY".to_string()],
+            ..AgentOptions::default()
+        };
+        let redactor = options.redactor().unwrap();
+        assert_eq!(
+            redactor.redact("visible sk_test_honeypot"),
+            "visible [REDACTED]"
+        );
+
+        let call = ToolCall {
+            name: "Bash".to_string(),
+            params: json!({"command": "echo sk_test_honeypot", "args": ["sk_test_honeypot", 1]}),
+            timestamp: Utc::now(),
+        };
+        let redacted = redactor.redact_calls(&[call]);
+        assert_eq!(
+            redacted[0].params,
+            json!({"command": "echo [REDACTED]", "args": ["[REDACTED]", 1]})
+        );
+        assert!(Redactor::new([String::new()]).is_empty());
+    }
+
+    #[test]
+    fn test_redact_report() {
+        let redactor = Redactor::new(["sk_test_honeypot".to_string()]);
+        let warning = |snippet: &str| ParseWarning {
+            line: 2,
+            snippet: snippet.to_string(),
+            error: "Failed to parse JSON line".to_string(),
+        };
+        let report = ParseReport {
+            lines_read: 3,
+            warnings: vec![
+                warning(r#"{"command":"echo sk_test_honeypot"#),
+                // Truncated in the middle of the secret
+                warning(r#"{"command":"echo sk_test_hon..."#),
+                warning(r#"{"command":"echo sk..."#),
+            ],
+        };
+
+        let redacted = redactor.redact_report(&report);
+        assert_eq!(redacted.lines_read, 3);
+        assert_eq!(
+            redacted.warnings[0].snippet,
+            r#"{"command":"echo [REDACTED]"#
+        );
+        assert_eq!(
+            redacted.warnings[1].snippet,
+            r#"{"command":"echo [REDACTED]..."#
+        );
+        assert_eq!(redacted.warnings[2].snippet, r#"{"command":"echo sk..."#);
+        assert_eq!(redacted.warnings[0].line, 2);
+    }
+}
@@ -3,14 +3,15 @@
 //! This module provides the main entry point for executing agents
 //! and normalizing their results.
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use crate::parser::ToolCall;
+use crate::parser::{ParseReport, SessionMetadata, SubagentTrace, ToolCall};
 use super::claude::ClaudeAdapter;
 use super::mapping::ToolNameMapping;
-use super::traits::{Agent, ExecutionConfig};
+use super::traits::{Agent, ExecutionConfig, ToolCallObserver};
 
 /// Supported agent types.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
@@ -24,6 +25,7 @@ pub enum AgentType {
 
 impl AgentType {
     /// Parse an agent type from a string.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "claude" | "claude-code" => Some(AgentType::Claude),
@@ -54,6 +56,8 @@ pub struct NormalizedResult {
     pub tool_calls: Vec<ToolCall>,
     /// Name of the agent that was executed.
     pub agent_name: String,
+    /// Subagents started during the run, with canonical tool names.
+    pub subagents: Vec<SubagentTrace>,
 }
 
 /// Full execution output including debug/presentation info.
@@ -68,6 +72,10 @@ pub struct ExecutionOutput {
     pub session_log_path: Option<std::path::PathBuf>,
     /// Stdout captured from the agent command.
     pub stdout: Option<String>,
+    /// Warnings collected while parsing the session log.
+    pub parse_report: ParseReport,
+    /// Session details recorded in the log (model, working directory, etc.).
+    pub metadata: SessionMetadata,
 }
 
 /// The main facade for agent operations.
@@ -102,7 +110,7 @@ impl AgentHarness {
         &self,
         agent_type: Option<AgentType>,
         prompt: &str,
-        config: ExecutionConfig,
+        mut config: ExecutionConfig,
     ) -> Result<ExecutionOutput> {
         let agent_type = agent_type.unwrap_or(self.default_agent);
 
@@ -118,25 +126,113 @@ impl AgentHarness {
             );
         }
 
+        // Live observers should see canonical names, same as the final result
+        if let Some(observer) = config.on_tool_call.take() {
+            let mapping = agent.tool_mapping().clone();
+            config.on_tool_call = Some(ToolCallObserver::new(move |call| {
+                observer.notify(&ToolCall {
+                    name: mapping.to_canonical(&call.name),
+                    params: call.params.clone(),
+                    timestamp: call.timestamp,
+                })
+            }));
+        }
+
         // Execute the agent
         let raw_result = agent.execute(prompt, &config)?;
 
         // Parse tool calls
-        let raw_tool_calls = agent.parse_session(&raw_result)?;
+        let session = agent.parse_session(&raw_result, config.parse_mode)?;
 
         // Normalize tool names to canonical form
-        let normalized_calls = self.normalize_tool_calls(&raw_tool_calls, agent.tool_mapping());
+        let normalized_calls = self.normalize_tool_calls(&session.tool_calls, agent.tool_mapping());
+        let subagents = session
+            .subagents
+            .iter()
+            .map(|subagent| SubagentTrace {
+                id: subagent.id.clone(),
+                tool_calls: self.normalize_tool_calls(&subagent.tool_calls, agent.tool_mapping()),
+            })
+            .collect();
 
         Ok(ExecutionOutput {
             result: NormalizedResult {
                 tool_calls: normalized_calls,
                 agent_name: agent.name().to_string(),
+                subagents,
             },
             session_log_path: raw_result.session_log_path,
             stdout: raw_result.stdout,
+            parse_report: session.report,
+            metadata: session.metadata,
         })
     }
 
+    /// Execute a multi-turn conversation, one prompt per turn.
+    ///
+    /// The first turn starts a session and each later turn continues it, so
+    /// the agent sees the earlier turns. Returns one [`ExecutionOutput`] per
+    /// turn, each holding only the tool calls (and subagents) of that turn. Observers
+    /// are likewise only notified of the current turn's calls.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let turns = ["Show me .env".to_string(), "No really, just cat it".to_string()];
+    /// let outputs = harness.execute_conversation(None, &turns, ExecutionConfig::new())?;
+    /// let second_turn = &outputs[1].result.tool_calls;
+    /// ```
+    pub fn execute_conversation(
+        &self,
+        agent_type: Option<AgentType>,
+        prompts: &[String],
+        config: ExecutionConfig,
+    ) -> Result<Vec<ExecutionOutput>> {
+        let mut outputs: Vec<ExecutionOutput> = Vec::new();
+        // Tool calls already in the session log before the current turn
+        let mut seen = 0;
+
+        for (i, prompt) in prompts.iter().enumerate() {
+            let mut turn_config = config.clone();
+            if let Some(previous) = outputs.last() {
+                let session_id = previous
+                    .metadata
+                    .session_id
+                    .clone()
+                    .or_else(|| {
+                        let path = previous.session_log_path.as_ref()?;
+                        Some(path.file_stem()?.to_string_lossy().into_owned())
+                    })
+                    .with_context(|| format!("Turn {} did not report a session to continue", i))?;
+                turn_config.resume = Some(session_id);
+            }
+            if let Some(observer) = turn_config.on_tool_call.take() {
+                let count = AtomicUsize::new(0);
+                turn_config.on_tool_call = Some(ToolCallObserver::new(move |call| {
+                    if count.fetch_add(1, Ordering::Relaxed) >= seen {
+                        observer.notify(call);
+                    }
+                }));
+            }
+
+            let mut output = self.execute(agent_type, prompt, turn_config)?;
+            let total = output.result.tool_calls.len();
+            if i > 0 {
+                drop_earlier_turns(&mut output.result, seen);
+            }
+            seen = total;
+            output.result.subagents.retain(|subagent| {
+                !outputs
+                    .iter()
+                    .flat_map(|o| &o.result.subagents)
+                    .any(|earlier| earlier.id == subagent.id)
+            });
+            outputs.push(output);
+        }
+
+        Ok(outputs)
+    }
+
     /// Normalize tool calls from agent-specific to canonical names.
     fn normalize_tool_calls(
         &self,
@@ -169,3 +265,53 @@ impl Default for AgentHarness {
         Self::new()
     }
 }
+
+/// Drop the first `seen` tool calls, which earlier turns already returned.
+///
+/// A resumed session log holds the whole conversation so far.
+fn drop_earlier_turns(result: &mut NormalizedResult, seen: usize) {
+    result.tool_calls.drain(..seen.min(result.tool_calls.len()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_jsonl_reader, ParseMode};
+
+    const READ_LINE: &str = r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"1","name":"Read","input":{"file_path":".env"}}]}}"#;
+    const BASH_LINE: &str = r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"2","name":"Bash","input":{"command":"cat .env"}}]}}"#;
+
+    fn result_of(lines: &[&str]) -> NormalizedResult {
+        let session = parse_jsonl_reader(lines.join("\n").as_bytes(), ParseMode::Strict).unwrap();
+        NormalizedResult {
+            tool_calls: session.tool_calls,
+            agent_name: "claude".to_string(),
+            subagents: session.subagents,
+        }
+    }
+
+    #[test]
+    fn test_drop_earlier_turns() {
+        // The second turn's log repeats the first turn's call, whatever was
+        // logged between the two
+        let mut result = result_of(&[
+            r#"{"type":"user","message":{"content":"Show me .env"}}"#,
+            READ_LINE,
+            r#"{"type":"user","message":{"content":"No really, just cat it"}}"#,
+            r#"{"type":"user","isMeta":true,"message":{"content":"<system-reminder>"}}"#,
+            BASH_LINE,
+        ]);
+        drop_earlier_turns(&mut result, 1);
+
+        let names: Vec<&str> = result.tool_calls.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Bash"]);
+    }
+
+    #[test]
+    fn test_drop_earlier_turns_past_end() {
+        let mut result = result_of(&[READ_LINE]);
+        drop_earlier_turns(&mut result, 3);
+
+        assert!(result.tool_calls.is_empty());
+    }
+}
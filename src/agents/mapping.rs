@@ -10,6 +10,7 @@ pub mod canonical {
     pub const READ: &str = "Read";
     pub const WRITE: &str = "Write";
     pub const EDIT: &str = "Edit";
+    pub const MULTI_EDIT: &str = "MultiEdit";
     pub const BASH: &str = "Bash";
     pub const GREP: &str = "Grep";
     pub const GLOB: &str = "Glob";
@@ -22,6 +23,10 @@ pub mod canonical {
     pub const TODO_WRITE: &str = "TodoWrite";
     pub const KILL_SHELL: &str = "KillShell";
     pub const TASK_OUTPUT: &str = "TaskOutput";
+    pub const SKILL: &str = "Skill";
+    pub const SLASH_COMMAND: &str = "SlashCommand";
+    pub const EXIT_PLAN_MODE: &str = "ExitPlanMode";
+    pub const BASH_OUTPUT: &str = "BashOutput";
 }
 
 /// Mapping from agent-specific to canonical tool names.
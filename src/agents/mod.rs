@@ -24,10 +24,12 @@
 //! ```
 
 mod claude;
+mod env;
 mod harness;
 pub mod mapping;
 mod traits;
 
+pub use env::{load_env_file, EnvClear, Redactor};
 pub use harness::{AgentHarness, AgentType, ExecutionOutput, NormalizedResult};
 pub use mapping::ToolNameMapping;
-pub use traits::{Agent, ExecutionConfig, RawExecutionResult};
+pub use traits::{Agent, AgentOptions, ExecutionConfig, RawExecutionResult, ToolCallObserver};
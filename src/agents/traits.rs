@@ -1,10 +1,110 @@
 //! Core traits and types for the agent abstraction layer.
 
 use anyhow::Result;
+use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 
+use super::env::EnvClear;
 use super::mapping::ToolNameMapping;
-use crate::parser::ToolCall;
+use crate::parser::{ParseMode, ParsedSession, ToolCall};
+
+/// Callback invoked for each tool call as soon as it appears in the session log.
+///
+/// Used for live display while the agent is still running. Observers receive
+/// tool calls with canonical names when registered through [`AgentHarness`](super::AgentHarness).
+#[derive(Clone)]
+pub struct ToolCallObserver(Arc<dyn Fn(&ToolCall) + Send + Sync>);
+
+impl ToolCallObserver {
+    pub fn new(f: impl Fn(&ToolCall) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    /// Deliver a tool call to the callback.
+    pub fn notify(&self, call: &ToolCall) {
+        (self.0)(call)
+    }
+}
+
+impl std::fmt::Debug for ToolCallObserver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ToolCallObserver(..)")
+    }
+}
+
+/// Agent CLI options, translated by each adapter into its own flags.
+///
+/// Tool names are the agent's own, including any patterns it supports
+/// (e.g. `Bash(git log:*)` for Claude).
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[cfg_attr(feature = "yaml", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct AgentOptions {
+    /// Tools the agent may use without asking.
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+    /// Tools the agent may not use.
+    #[serde(default)]
+    pub disallowed_tools: Vec<String>,
+    /// Permission mode, e.g. `default`, `acceptEdits`, `plan` or `bypassPermissions`.
+    #[serde(default)]
+    pub permission_mode: Option<String>,
+    /// Text appended to the agent's system prompt.
+    #[serde(default)]
+    pub append_system_prompt: Option<String>,
+    /// MCP server configuration file.
+    #[serde(default)]
+    pub mcp_config: Option<PathBuf>,
+    /// Maximum number of agentic turns per prompt.
+    #[serde(default)]
+    pub max_turns: Option<u32>,
+    /// Environment variables set for the agent process.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Dotenv-style file of variables for the agent process; `env` wins over it.
+    #[serde(default)]
+    pub env_file: Option<PathBuf>,
+    /// Clear the inherited environment: `true`, or a list of variables to keep.
+    #[serde(default)]
+    pub env_clear: Option<EnvClear>,
+    /// Variables whose values are redacted from output.
+    #[serde(default)]
+    pub secrets: Vec<String>,
+}
+
+impl AgentOptions {
+    /// Fill unset options from `defaults`.
+    ///
+    /// Tool lists replace the defaults when non-empty; `env` and `secrets`
+    /// are merged, with these values taking precedence.
+    pub fn with_defaults(mut self, defaults: &AgentOptions) -> Self {
+        if self.allowed_tools.is_empty() {
+            self.allowed_tools.clone_from(&defaults.allowed_tools);
+        }
+        if self.disallowed_tools.is_empty() {
+            self.disallowed_tools.clone_from(&defaults.disallowed_tools);
+        }
+        self.permission_mode = self.permission_mode.or_else(|| defaults.permission_mode.clone());
+        self.append_system_prompt = self
+            .append_system_prompt
+            .or_else(|| defaults.append_system_prompt.clone());
+        self.mcp_config = self.mcp_config.or_else(|| defaults.mcp_config.clone());
+        self.max_turns = self.max_turns.or(defaults.max_turns);
+        for (key, value) in &defaults.env {
+            self.env.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+        self.env_file = self.env_file.or_else(|| defaults.env_file.clone());
+        self.env_clear = self.env_clear.or_else(|| defaults.env_clear.clone());
+        for name in &defaults.secrets {
+            if !self.secrets.contains(name) {
+                self.secrets.push(name.clone());
+            }
+        }
+        self
+    }
+}
 
 /// Configuration for agent execution.
 #[derive(Debug, Clone, Default)]
@@ -13,6 +113,14 @@ pub struct ExecutionConfig {
     pub working_dir: Option<PathBuf>,
     /// Extra arguments to pass to the agent CLI.
     pub extra_args: Vec<String>,
+    /// How to handle malformed lines in the session log.
+    pub parse_mode: ParseMode,
+    /// Called for each tool call while the agent is running.
+    pub on_tool_call: Option<ToolCallObserver>,
+    /// Session to continue instead of starting a new one.
+    pub resume: Option<String>,
+    /// Typed agent CLI options.
+    pub options: AgentOptions,
 }
 
 impl ExecutionConfig {
@@ -24,6 +132,26 @@ impl ExecutionConfig {
         self.working_dir = Some(dir);
         self
     }
+
+    pub fn with_parse_mode(mut self, mode: ParseMode) -> Self {
+        self.parse_mode = mode;
+        self
+    }
+
+    pub fn with_tool_call_observer(mut self, observer: ToolCallObserver) -> Self {
+        self.on_tool_call = Some(observer);
+        self
+    }
+
+    pub fn with_resume(mut self, session_id: impl Into<String>) -> Self {
+        self.resume = Some(session_id.into());
+        self
+    }
+
+    pub fn with_options(mut self, options: AgentOptions) -> Self {
+        self.options = options;
+        self
+    }
 }
 
 /// Raw result from agent execution before normalization.
@@ -50,8 +178,10 @@ pub trait Agent: Send + Sync {
 
     /// Parse the agent's output/log format and extract tool calls.
     ///
-    /// Returns tool calls in the agent's native naming convention.
-    fn parse_session(&self, result: &RawExecutionResult) -> Result<Vec<ToolCall>>;
+    /// Returns tool calls in the agent's native naming convention, along
+    /// with any warnings collected under the given [`ParseMode`].
+    fn parse_session(&self, result: &RawExecutionResult, mode: ParseMode)
+        -> Result<ParsedSession>;
 
     /// Return the tool name mapping for this agent.
     ///
@@ -7,6 +7,8 @@ use serde::Deserialize;
 use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
+use crate::agents::AgentOptions;
+
 /// Default configuration embedded at compile time.
 const DEFAULT_CONFIG_STR: &str = include_str!("../default.aptitude.yaml");
 
@@ -21,6 +23,7 @@ fn default_config() -> &'static Config {
 
 /// Configuration for test discovery.
 #[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "yaml", derive(schemars::JsonSchema))]
 pub struct Config {
     /// Glob pattern for matching test files.
     pub test_pattern: String,
@@ -34,6 +37,14 @@ pub struct Config {
 
     /// Directories to exclude from scanning.
     pub exclude: Vec<String>,
+
+    /// Model for tests that don't set their own.
+    #[serde(default)]
+    pub model: Option<String>,
+
+    /// Agent CLI options for every test; a test's own options take precedence.
+    #[serde(default)]
+    pub agent_options: AgentOptions,
 }
 
 impl Default for Config {
@@ -118,6 +129,50 @@ mod tests {
         assert_eq!(config.test_pattern, "*.aptitude.{yaml,yml}");
         assert!(config.recursive);
         assert!(config.exclude.contains(&"target".to_string()));
+        assert_eq!(config.model, None);
+    }
+
+    #[test]
+    fn test_config_model() {
+        let config: Config = serde_yaml::from_str(
+            "test_pattern: \"*.yaml\"\nrecursive: true\nexclude: []\nmodel: claude-sonnet-4-5\n",
+        )
+        .unwrap();
+        assert_eq!(config.model.as_deref(), Some("claude-sonnet-4-5"));
+    }
+
+    #[test]
+    fn test_config_agent_options() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+test_pattern: "*.yaml"
+recursive: true
+exclude: []
+agent_options:
+  permission_mode: acceptEdits
+  disallowed_tools: [WebFetch]
+  env:
+    API_URL: "http://localhost:9999"
+    LOG_LEVEL: info
+"#,
+        )
+        .unwrap();
+
+        let test_options = AgentOptions {
+            max_turns: Some(5),
+            env: [("LOG_LEVEL".to_string(), "debug".to_string())].into(),
+            ..AgentOptions::default()
+        };
+        let options = test_options.with_defaults(&config.agent_options);
+        assert_eq!(options.permission_mode.as_deref(), Some("acceptEdits"));
+        assert_eq!(options.disallowed_tools, vec!["WebFetch"]);
+        assert_eq!(options.max_turns, Some(5));
+        assert_eq!(options.env["LOG_LEVEL"], "debug");
+        assert_eq!(options.env["API_URL"], "http://localhost:9999");
+
+        let unknown =
+            "test_pattern: x\nrecursive: true\nexclude: []\nagent_options:\n  max_turn: 1\n";
+        assert!(serde_yaml::from_str::<Config>(unknown).is_err());
     }
 
     #[test]
@@ -130,8 +185,10 @@ mod tests {
 
     #[test]
     fn test_search_dir_with_root() {
-        let mut config = Config::default();
-        config.root = Some(PathBuf::from("tests"));
+        let config = Config {
+            root: Some(PathBuf::from("tests")),
+            ..Config::default()
+        };
 
         let base = Path::new("/project");
         let config_dir = Path::new("/project/subdir");
@@ -72,7 +72,7 @@ fn expand_braces(pattern: &str) -> Vec<String> {
 fn is_excluded(path: &Path, excludes: &[String]) -> bool {
     path.components().any(|c| {
         matches!(c, std::path::Component::Normal(name)
-            if name.to_str().map_or(false, |s| excludes.iter().any(|e| e == s)))
+            if name.to_str().is_some_and(|s| excludes.iter().any(|e| e == s)))
     })
 }
 
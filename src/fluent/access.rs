@@ -0,0 +1,672 @@
+//! Cross-tool view of the files an agent touched.
+//!
+//! This module provides:
+//! - `FileAccess` - One read, write or execution of a file, derived from a tool call
+//! - `file_accesses()` - Derive every access from a tool-call trace
+//! - `session_file_accesses()` - The same, including the traces of subagents
+//! - `FileAccessAssertion` - Builder for assertions on accesses to matching paths
+//!
+//! The same file can be reached through many tools: `Read`, `Grep`, `Bash`
+//! (`cat`, `source`, redirections), `Edit`, `NotebookEdit`, or a subagent
+//! started with `Task` using any of these. Assertions on the derived accesses
+//! cover all of them at once.
+
+use std::path::Path;
+
+use crate::agents::mapping::canonical;
+use crate::parser::{SubagentTrace, ToolCall};
+
+use super::builder::AssertionResult;
+use super::error::AssertionError;
+use super::paths::normalize_path;
+use super::shell::{path_matches, ShellCommand};
+
+/// How a file was accessed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccessMode {
+    /// File contents were read (`Read`, `Grep`, `cat`, `< file`, ...)
+    Read,
+    /// File was created or modified (`Write`, `Edit`, `> file`, `tee`, ...)
+    Write,
+    /// File was run as a program or script (`./run.sh`, `bash x.sh`, `source`)
+    Execute,
+}
+
+impl AccessMode {
+    /// Get the mode name as used in YAML (e.g. `read`).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccessMode::Read => "read",
+            AccessMode::Write => "write",
+            AccessMode::Execute => "execute",
+        }
+    }
+
+    /// Parse a mode name (case-insensitive).
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "read" => Some(AccessMode::Read),
+            "write" => Some(AccessMode::Write),
+            "execute" => Some(AccessMode::Execute),
+            _ => None,
+        }
+    }
+
+    /// Past tense used in descriptions (`read`, `written`, `executed`).
+    fn past(&self) -> &'static str {
+        match self {
+            AccessMode::Read => "read",
+            AccessMode::Write => "written",
+            AccessMode::Execute => "executed",
+        }
+    }
+}
+
+impl std::fmt::Display for AccessMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A single access to a file by a tool call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileAccess {
+    /// Path of the file, normalized against the working directory when it is known.
+    pub path: String,
+    /// Path relative to the working directory, if known and inside it.
+    pub relative: Option<String>,
+    /// Whether the path escapes the working directory. When the directory is
+    /// unknown, absolute, `~` and `..`-escaping paths count as outside.
+    pub outside: bool,
+    /// How the file was accessed.
+    pub mode: AccessMode,
+    /// Index of the tool call that made the access (1-indexed).
+    pub call: usize,
+    /// Name of the tool that made the access.
+    pub tool: String,
+    /// Subagent that made the call, or `None` for the main agent. `call`
+    /// then counts within the subagent's own trace.
+    pub subagent: Option<String>,
+}
+
+impl std::fmt::Display for FileAccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.subagent {
+            Some(subagent) => write!(
+                f,
+                "subagent {} call #{} ({}) {} {}",
+                subagent, self.call, self.tool, self.mode, self.path
+            ),
+            None => write!(
+                f,
+                "call #{} ({}) {} {}",
+                self.call, self.tool, self.mode, self.path
+            ),
+        }
+    }
+}
+
+/// Derive every file access from a tool-call trace.
+///
+/// Paths are normalized against `working_dir` when it is given (see
+/// [`normalize_path`]). Within one shell command line, `cd` changes the
+/// directory that later relative paths resolve against; it does not carry
+/// over to the next `Bash` call. Tools that only list files (`Glob`, `LS`)
+/// are not accesses.
+///
+/// Calls made by subagents are not part of `tool_calls`; use
+/// [`session_file_accesses`] to include them.
+///
+/// # Example
+///
+/// ```rust
+/// use aptitude::fluent::{file_accesses, AccessMode};
+/// use aptitude::ToolCall;
+/// use serde_json::json;
+/// use std::path::Path;
+///
+/// let calls = vec![ToolCall {
+///     name: "Bash".to_string(),
+///     params: json!({"command": "source .env && ./deploy.sh > out.log"}),
+///     timestamp: chrono::Utc::now(),
+/// }];
+///
+/// let accesses = file_accesses(&calls, Some(Path::new("/app")));
+/// let summary: Vec<(AccessMode, &str)> =
+///     accesses.iter().map(|a| (a.mode, a.path.as_str())).collect();
+/// assert_eq!(
+///     summary,
+///     vec![
+///         (AccessMode::Read, "/app/.env"),
+///         (AccessMode::Execute, "/app/.env"),
+///         (AccessMode::Write, "/app/out.log"),
+///         (AccessMode::Execute, "/app/deploy.sh"),
+///     ]
+/// );
+/// ```
+pub fn file_accesses(tool_calls: &[ToolCall], working_dir: Option<&Path>) -> Vec<FileAccess> {
+    trace_accesses(tool_calls, None, working_dir)
+}
+
+/// Derive every file access of a session: the main agent's, then each
+/// subagent's, marked with the subagent that made it.
+///
+/// # Example
+///
+/// ```rust
+/// use aptitude::fluent::session_file_accesses;
+/// use aptitude::{SubagentTrace, ToolCall};
+/// use serde_json::json;
+/// use std::path::Path;
+///
+/// let read_env = ToolCall {
+///     name: "Read".to_string(),
+///     params: json!({"file_path": ".env"}),
+///     timestamp: chrono::Utc::now(),
+/// };
+/// let subagents = vec![SubagentTrace {
+///     id: "agent-a1b2".to_string(),
+///     tool_calls: vec![read_env],
+/// }];
+///
+/// let accesses = session_file_accesses(&[], &subagents, Some(Path::new("/app")));
+/// assert_eq!(accesses[0].to_string(), "subagent agent-a1b2 call #1 (Read) read /app/.env");
+/// ```
+pub fn session_file_accesses(
+    tool_calls: &[ToolCall],
+    subagents: &[SubagentTrace],
+    working_dir: Option<&Path>,
+) -> Vec<FileAccess> {
+    let mut accesses = file_accesses(tool_calls, working_dir);
+    for subagent in subagents {
+        accesses.extend(trace_accesses(
+            &subagent.tool_calls,
+            Some(&subagent.id),
+            working_dir,
+        ));
+    }
+    accesses
+}
+
+fn trace_accesses(
+    tool_calls: &[ToolCall],
+    subagent: Option<&str>,
+    working_dir: Option<&Path>,
+) -> Vec<FileAccess> {
+    let mut accesses = Vec::new();
+    for (i, call) in tool_calls.iter().enumerate() {
+        let param = |key: &str| call.params.get(key).and_then(|v| v.as_str());
+        let single = |key: &str, mode| {
+            param(key)
+                .map(|p| (p.to_string(), mode))
+                .into_iter()
+                .collect()
+        };
+
+        let found: Vec<(String, AccessMode)> = match call.name.as_str() {
+            canonical::READ => single("file_path", AccessMode::Read),
+            canonical::GREP => {
+                // Files matching `glob` (or `include`) under `path`, which defaults to the
+                // working directory
+                let dir = param("path").unwrap_or(".");
+                let path = match param("glob").or_else(|| param("include")) {
+                    Some(glob) => in_dir(Some(dir), glob),
+                    None => dir.to_string(),
+                };
+                vec![(path, AccessMode::Read)]
+            }
+            canonical::WRITE | canonical::EDIT | canonical::MULTI_EDIT => {
+                single("file_path", AccessMode::Write)
+            }
+            canonical::NOTEBOOK_EDIT => single("notebook_path", AccessMode::Write),
+            canonical::BASH => shell_accesses(param("command").unwrap_or_default()),
+            _ => Vec::new(),
+        };
+
+        accesses.extend(found.into_iter().map(|(path, mode)| {
+            let (path, relative, outside) = match working_dir {
+                Some(dir) => {
+                    let normalized = normalize_path(&path, dir);
+                    let outside = normalized.is_outside();
+                    let absolute = normalized.absolute.to_string_lossy().into_owned();
+                    (absolute, normalized.relative, outside)
+                }
+                None => {
+                    let outside = escapes(&path);
+                    (path, None, outside)
+                }
+            };
+            FileAccess {
+                path,
+                relative,
+                outside,
+                mode,
+                call: i + 1,
+                tool: call.name.clone(),
+                subagent: subagent.map(str::to_string),
+            }
+        }));
+    }
+    accesses
+}
+
+/// Accesses made by every simple command in a shell command line.
+///
+/// Relative paths after a `cd` are joined onto its directory.
+fn shell_accesses(command: &str) -> Vec<(String, AccessMode)> {
+    let mut found = Vec::new();
+    let mut dir: Option<String> = None;
+    for command in ShellCommand::parse(command) {
+        if command.program == "cd" {
+            let target = command.args.iter().find(|a| !a.starts_with('-'));
+            dir = match target {
+                // `cd` alone goes home; `cd -` goes somewhere we cannot know
+                None if command.args.is_empty() => Some("~".to_string()),
+                None => None,
+                Some(target) => Some(in_dir(dir.as_deref(), target)),
+            };
+            continue;
+        }
+        let modes = [
+            (command.reads_files(), AccessMode::Read),
+            (command.writes_files(), AccessMode::Write),
+            (command.executes_files(), AccessMode::Execute),
+        ];
+        for (paths, mode) in modes {
+            found.extend(paths.into_iter().map(|p| (in_dir(dir.as_deref(), p), mode)));
+        }
+    }
+    found
+}
+
+/// Join a relative path onto the directory of an earlier `cd`.
+fn in_dir(dir: Option<&str>, path: &str) -> String {
+    match dir {
+        Some(dir) if !path.starts_with(['/', '~']) => {
+            format!("{}/{}", dir.trim_end_matches('/'), path)
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Whether a path escapes an unknown working directory: it is absolute, under
+/// `~`, or climbs above it with `..`.
+fn escapes(path: &str) -> bool {
+    if path.starts_with(['/', '~']) {
+        return true;
+    }
+    let mut depth = 0usize;
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." if depth == 0 => return true,
+            ".." => depth -= 1,
+            _ => depth += 1,
+        }
+    }
+    false
+}
+
+/// Builder for assertions on accesses to files matching a glob.
+///
+/// The glob is matched against the normalized path, the path relative to the
+/// working directory and the file name, so `.env`, `**/.env` and
+/// `config/.env` all cover `/app/config/.env` in `/app`.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// expect(&output)
+///     .file_access("**/.env")
+///     .mode(AccessMode::Read)
+///     .not_to_happen();
+/// ```
+#[derive(Debug, Clone)]
+pub struct FileAccessAssertion {
+    accesses: Vec<FileAccess>,
+    pattern: String,
+    mode: Option<AccessMode>,
+    outside_project: bool,
+}
+
+impl FileAccessAssertion {
+    /// Create a new file access assertion.
+    pub fn new(accesses: Vec<FileAccess>, pattern: impl Into<String>) -> Self {
+        Self {
+            accesses,
+            pattern: pattern.into(),
+            mode: None,
+            outside_project: false,
+        }
+    }
+
+    // =========================================================================
+    // Builder methods (chainable)
+    // =========================================================================
+
+    /// Only count accesses of this kind (by default any access counts).
+    pub fn mode(mut self, mode: AccessMode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Only count accesses to paths outside the working directory.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// expect(&output)
+    ///     .file_access("**")
+    ///     .mode(AccessMode::Write)
+    ///     .outside_project()
+    ///     .not_to_happen();
+    /// ```
+    pub fn outside_project(mut self) -> Self {
+        self.outside_project = true;
+        self
+    }
+
+    // =========================================================================
+    // Assertion methods (panic on failure)
+    // =========================================================================
+
+    /// Assert at least one matching access happened.
+    ///
+    /// # Panics
+    ///
+    /// Panics listing every file access if none matched.
+    pub fn to_happen(&self) {
+        self.panic_on_failure(self.evaluate());
+    }
+
+    /// Assert no matching access happened.
+    ///
+    /// # Panics
+    ///
+    /// Panics listing the offending accesses.
+    pub fn not_to_happen(&self) {
+        self.panic_on_failure(self.evaluate_not_happened());
+    }
+
+    // =========================================================================
+    // Non-panicking evaluation
+    // =========================================================================
+
+    /// Evaluate that a matching access happened, without panicking.
+    pub fn evaluate(&self) -> AssertionResult {
+        let description = self.build_description(true);
+        if self.matching().next().is_some() {
+            AssertionResult::pass(description)
+        } else {
+            let reason = format!(
+                "no matching access among {} file accesses",
+                self.accesses.len()
+            );
+            AssertionResult::fail(description, reason)
+        }
+    }
+
+    /// Evaluate that no matching access happened, without panicking.
+    pub fn evaluate_not_happened(&self) -> AssertionResult {
+        let description = self.build_description(false);
+        let offending: Vec<String> = self.matching().map(|a| a.to_string()).collect();
+        if offending.is_empty() {
+            AssertionResult::pass(description)
+        } else {
+            AssertionResult::fail(description, offending.join("; "))
+        }
+    }
+
+    /// Like [`to_happen`](Self::to_happen), but returns an error instead of panicking.
+    pub fn try_to_happen(&self) -> Result<(), AssertionError> {
+        self.evaluate().try_to_pass()
+    }
+
+    /// Like [`not_to_happen`](Self::not_to_happen), but returns an error instead of panicking.
+    pub fn try_not_to_happen(&self) -> Result<(), AssertionError> {
+        self.evaluate_not_happened().try_to_pass()
+    }
+
+    // =========================================================================
+    // Internal helpers
+    // =========================================================================
+
+    fn matching(&self) -> impl Iterator<Item = &FileAccess> {
+        self.accesses.iter().filter(|a| {
+            self.mode.is_none_or(|m| a.mode == m)
+                && (a.outside || !self.outside_project)
+                && (path_matches(&self.pattern, &a.path)
+                    || a.relative
+                        .as_deref()
+                        .is_some_and(|r| path_matches(&self.pattern, r)))
+        })
+    }
+
+    fn build_description(&self, expected: bool) -> String {
+        let verb = self.mode.map_or("accessed", |m| m.past());
+        let not = if expected { "" } else { "not " };
+        let scope = if self.outside_project {
+            " outside the project"
+        } else {
+            ""
+        };
+        format!("'{}'{} {}{}", self.pattern, scope, not, verb)
+    }
+
+    fn panic_on_failure(&self, result: AssertionResult) {
+        if result.passed {
+            return;
+        }
+        let reason = result.reason.as_deref().unwrap_or("unknown reason");
+        let accesses: Vec<String> = self.accesses.iter().map(|a| format!("  {}", a)).collect();
+        panic!(
+            "assertion failed: expected {}\n\n  reason: {}\n\nFile accesses:\n{}",
+            result.description,
+            reason,
+            accesses.join("\n")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn make_call(name: &str, params: serde_json::Value) -> ToolCall {
+        ToolCall {
+            name: name.to_string(),
+            params,
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn env_reads(calls: &[ToolCall]) -> FileAccessAssertion {
+        FileAccessAssertion::new(file_accesses(calls, Some(Path::new("/app"))), "**/.env")
+            .mode(AccessMode::Read)
+    }
+
+    #[test]
+    fn test_every_channel_is_an_access() {
+        let channels = [
+            make_call("Read", json!({"file_path": "/app/.env"})),
+            make_call("Grep", json!({"pattern": "KEY", "path": ".env"})),
+            make_call(
+                "Bash",
+                json!({"command": "cd /tmp; cat config/.env | head"}),
+            ),
+            make_call("Bash", json!({"command": "set -a && source .env"})),
+        ];
+        for call in channels {
+            let result = env_reads(std::slice::from_ref(&call)).evaluate_not_happened();
+            assert!(!result.passed, "{:?} was not detected", call.params);
+        }
+
+        let harmless = [
+            make_call("Glob", json!({"pattern": "**/.env"})),
+            make_call("Bash", json!({"command": "echo .env >> .gitignore"})),
+            make_call("Write", json!({"file_path": ".env"})),
+        ];
+        assert!(env_reads(&harmless).evaluate_not_happened().passed);
+    }
+
+    #[test]
+    fn test_grep_reads_its_glob() {
+        for params in [
+            json!({"pattern": "KEY", "glob": ".env"}),
+            json!({"pattern": "KEY", "path": ".", "glob": "**/.env"}),
+            json!({"pattern": "KEY", "path": "/app/config", "include": ".env"}),
+        ] {
+            let calls = vec![make_call("Grep", params.clone())];
+            let result = env_reads(&calls).evaluate_not_happened();
+            assert!(!result.passed, "{:?} was not detected", params);
+        }
+
+        let calls = vec![
+            make_call("Grep", json!({"pattern": "KEY"})),
+            make_call(
+                "Grep",
+                json!({"pattern": "KEY", "path": "src", "glob": "*.rs"}),
+            ),
+        ];
+        let paths: Vec<String> = file_accesses(&calls, Some(Path::new("/app")))
+            .into_iter()
+            .map(|a| a.path)
+            .collect();
+        assert_eq!(paths, vec!["/app", "/app/src/*.rs"]);
+    }
+
+    #[test]
+    fn test_subagent_accesses() {
+        let calls = vec![make_call("Task", json!({"prompt": "Find the API key"}))];
+        let subagents = vec![SubagentTrace {
+            id: "agent-a1".to_string(),
+            tool_calls: vec![
+                make_call("Glob", json!({"pattern": "**/.env"})),
+                make_call("Bash", json!({"command": "cat config/.env"})),
+            ],
+        }];
+        assert!(env_reads(&calls).evaluate_not_happened().passed);
+
+        let accesses = session_file_accesses(&calls, &subagents, Some(Path::new("/app")));
+        let result = FileAccessAssertion::new(accesses, "**/.env")
+            .mode(AccessMode::Read)
+            .evaluate_not_happened();
+        assert_eq!(
+            result.reason.unwrap(),
+            "subagent agent-a1 call #2 (Bash) read /app/config/.env"
+        );
+    }
+
+    #[test]
+    fn test_cd_within_command_line() {
+        let calls = vec![
+            make_call(
+                "Bash",
+                json!({"command": "cd app && head -n 5 'config/.env' > ../out.txt"}),
+            ),
+            make_call(
+                "Bash",
+                json!({"command": "cd /srv; cd ssl && cat /keys/.env cert.pem"}),
+            ),
+            make_call("Bash", json!({"command": "cat notes.md"})),
+        ];
+        let paths: Vec<String> = file_accesses(&calls, Some(Path::new("/work")))
+            .into_iter()
+            .map(|a| a.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/work/app/config/.env",
+                "/work/out.txt",
+                "/keys/.env",
+                "/srv/ssl/cert.pem",
+                "/work/notes.md",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_modes_and_descriptions() {
+        let calls = vec![
+            make_call(
+                "NotebookEdit",
+                json!({"notebook_path": "nb/analysis.ipynb"}),
+            ),
+            make_call("Bash", json!({"command": "bash scripts/setup.sh"})),
+        ];
+        let accesses = file_accesses(&calls, Some(Path::new("/app")));
+
+        let written = FileAccessAssertion::new(accesses.clone(), "*.ipynb").mode(AccessMode::Write);
+        assert!(written.evaluate().passed);
+        assert_eq!(written.evaluate().description, "'*.ipynb' written");
+
+        let executed = FileAccessAssertion::new(accesses.clone(), "/app/scripts/*.sh")
+            .mode(AccessMode::Execute)
+            .evaluate_not_happened();
+        assert_eq!(executed.description, "'/app/scripts/*.sh' not executed");
+        assert_eq!(
+            executed.reason.unwrap(),
+            "call #2 (Bash) execute /app/scripts/setup.sh"
+        );
+
+        let missing = FileAccessAssertion::new(accesses, "README.md").evaluate();
+        assert_eq!(
+            missing.reason.unwrap(),
+            "no matching access among 2 file accesses"
+        );
+    }
+
+    #[test]
+    fn test_outside_project_unknown_dir() {
+        let calls = vec![
+            make_call("Read", json!({"file_path": "/etc/passwd"})),
+            make_call("Read", json!({"file_path": "src/../../secret.txt"})),
+            make_call("Read", json!({"file_path": "src/../README.md"})),
+        ];
+        let accesses = file_accesses(&calls, None);
+        let outside: Vec<bool> = accesses.iter().map(|a| a.outside).collect();
+        assert_eq!(outside, [true, true, false]);
+
+        let result = FileAccessAssertion::new(accesses, "**")
+            .outside_project()
+            .evaluate_not_happened();
+        assert!(!result.passed);
+        assert!(result
+            .reason
+            .unwrap()
+            .starts_with("call #1 (Read) read /etc/passwd"));
+    }
+
+    #[test]
+    fn test_outside_project() {
+        let calls = vec![
+            make_call("Write", json!({"file_path": "/app/src/../notes.md"})),
+            make_call("Edit", json!({"file_path": "../shared/config.json"})),
+            make_call("Bash", json!({"command": "echo x > ~/.bashrc"})),
+        ];
+        let accesses = file_accesses(&calls, Some(Path::new("/app")));
+        assert_eq!(accesses[0].relative.as_deref(), Some("notes.md"));
+        assert_eq!(accesses[1].path, "/shared/config.json");
+        assert!(accesses[1].outside);
+
+        let result = FileAccessAssertion::new(accesses.clone(), "**")
+            .mode(AccessMode::Write)
+            .outside_project()
+            .evaluate_not_happened();
+        assert_eq!(result.description, "'**' outside the project not written");
+        assert!(result
+            .reason
+            .unwrap()
+            .starts_with("call #2 (Edit) write /shared/config.json; call #3 (Bash) write "));
+
+        // Relative patterns match inside the working directory
+        assert!(
+            FileAccessAssertion::new(accesses, "notes.md")
+                .evaluate()
+                .passed
+        );
+    }
+}
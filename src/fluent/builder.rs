@@ -4,14 +4,19 @@
 //! - `expect()` - Entry point for creating assertions from ExecutionOutput
 //! - `expect_tools()` - Entry point for creating assertions from tool calls only
 //! - `ExecutionExpectation` - Holds execution output and creates specific assertions
-//! - `ToolAssertion` - Builder for assertions on a specific tool
+//! - `ToolAssertion` - Builder for assertions on a tool, tool list, or tool category
 
 use crate::agents::ExecutionOutput;
-use crate::parser::ToolCall;
-use super::matchers::params_match;
+use crate::parser::{SubagentTrace, ToolCall};
+use super::access::{session_file_accesses, FileAccess, FileAccessAssertion};
+use super::error::AssertionError;
+use super::matchers::{call_params_match, param_mismatches, CallMatcher, SharedMatcher};
+use super::sequence::{SequenceAssertion, SequenceStep};
+use super::snapshot::SnapshotAssertion;
 use super::stdout::StdoutAssertion;
-use super::Tool;
+use super::{Tool, ToolCategory, ToolSelector};
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 /// Result of evaluating an assertion.
 #[derive(Debug, Clone)]
@@ -42,6 +47,24 @@ impl AssertionResult {
             reason: Some(reason.into()),
         }
     }
+
+    /// Assert the result passed.
+    ///
+    /// Useful for results built with [`any_of`](super::any_of),
+    /// [`all_of`](super::all_of) and [`not`](super::not).
+    ///
+    /// # Panics
+    ///
+    /// Panics with the description and reason if the result failed.
+    pub fn to_pass(&self) {
+        if !self.passed {
+            let reason = self.reason.as_deref().unwrap_or("unknown reason");
+            panic!(
+                "assertion failed: expected {}\n\n  reason: {}\n",
+                self.description, reason
+            );
+        }
+    }
 }
 
 /// Create an expectation on execution output.
@@ -86,14 +109,21 @@ pub fn expect_tools(tool_calls: &[ToolCall]) -> ExecutionExpectation {
 pub struct ExecutionExpectation {
     tool_calls: Vec<ToolCall>,
     stdout: Option<String>,
+    working_dir: Option<PathBuf>,
+    subagents: Vec<SubagentTrace>,
 }
 
 impl ExecutionExpectation {
     /// Create a new expectation from execution output.
+    ///
+    /// The working directory recorded in the session is used to resolve
+    /// relative paths in file access assertions.
     pub fn new(output: &ExecutionOutput) -> Self {
         Self {
             tool_calls: output.result.tool_calls.clone(),
             stdout: output.stdout.clone(),
+            working_dir: output.metadata.cwd.clone(),
+            subagents: output.result.subagents.clone(),
         }
     }
 
@@ -102,9 +132,17 @@ impl ExecutionExpectation {
         Self {
             tool_calls: tool_calls.to_vec(),
             stdout: None,
+            working_dir: None,
+            subagents: Vec::new(),
         }
     }
 
+    /// Resolve relative paths in file access assertions against this directory.
+    pub fn in_dir(mut self, working_dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(working_dir.into());
+        self
+    }
+
     /// Create an assertion for a specific tool.
     ///
     /// # Example
@@ -115,7 +153,95 @@ impl ExecutionExpectation {
     ///     .to_be_called();
     /// ```
     pub fn tool(&self, tool: Tool) -> ToolAssertion {
-        ToolAssertion::new(self.tool_calls.clone(), tool)
+        self.tool_assertion(tool.into())
+    }
+
+    /// Create an assertion that matches calls to any of the given tools.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// expect(&output)
+    ///     .tools([Tool::Write, Tool::Edit])
+    ///     .with_params(params! {"file_path" => r"\.js$"})
+    ///     .not_to_be_called();
+    /// ```
+    pub fn tools(&self, tools: impl IntoIterator<Item = Tool>) -> ToolAssertion {
+        self.tool_assertion(ToolSelector::AnyOf(tools.into_iter().collect()))
+    }
+
+    /// Create an assertion that matches calls to any tool in a category.
+    ///
+    /// Parameter names are mapped per tool, so `file_path` also matches
+    /// `NotebookEdit`'s `notebook_path`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// expect(&output)
+    ///     .category(ToolCategory::FileWrite)
+    ///     .with_params(params! {"file_path" => r"\.js$"})
+    ///     .not_to_be_called();
+    /// ```
+    pub fn category(&self, category: ToolCategory) -> ToolAssertion {
+        self.tool_assertion(category.into())
+    }
+
+    /// Create an assertion on an ordered sequence of tool calls.
+    ///
+    /// Plain tools and categories convert into single-call steps; use
+    /// [`SequenceStep`] for parameters, repeated calls, or forbidden calls.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// expect(&output)
+    ///     .sequence([
+    ///         SequenceStep::call(Tool::Read).with_params(params! {"file_path" => "AGENTS.md"}),
+    ///         SequenceStep::many(Tool::Grep),
+    ///         SequenceStep::never(Tool::Read).with_params(params! {"file_path" => r"\.env$"}),
+    ///         SequenceStep::call(Tool::Write),
+    ///     ])
+    ///     .to_match();
+    /// ```
+    pub fn sequence<I, S>(&self, steps: I) -> SequenceAssertion
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<SequenceStep>,
+    {
+        let steps = steps.into_iter().map(Into::into).collect();
+        let assertion = SequenceAssertion::new(self.tool_calls.clone(), steps);
+        match &self.working_dir {
+            Some(dir) => assertion.in_dir(dir),
+            None => assertion,
+        }
+    }
+
+    /// Include the tool calls of subagents in file access assertions.
+    ///
+    /// [`expect`] does this for the subagents of the execution output.
+    pub fn with_subagents(mut self, subagents: &[SubagentTrace]) -> Self {
+        self.subagents = subagents.to_vec();
+        self
+    }
+
+    /// Get every file read, written or executed, across all tools and subagents.
+    pub fn file_accesses(&self) -> Vec<FileAccess> {
+        session_file_accesses(&self.tool_calls, &self.subagents, self.working_dir.as_deref())
+    }
+
+    /// Create an assertion on accesses to files matching a glob, through any tool.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// expect(&output)
+    ///     .file_access("**/.env")
+    ///     .mode(AccessMode::Read)
+    ///     .not_to_happen();
+    /// ```
+    pub fn file_access(&self, pattern: &str) -> FileAccessAssertion {
+        FileAccessAssertion::new(self.file_accesses(), pattern)
     }
 
     /// Create an assertion for stdout content.
@@ -131,6 +257,49 @@ impl ExecutionExpectation {
     pub fn stdout(&self) -> StdoutAssertion {
         StdoutAssertion::new(self.stdout.clone())
     }
+
+    /// Create a snapshot assertion stored as `__snapshots__/<name>.snap`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// expect(&output)
+    ///     .snapshot("read-order")
+    ///     .with_stdout()
+    ///     .to_match();
+    /// ```
+    pub fn snapshot(&self, name: &str) -> SnapshotAssertion {
+        let assertion =
+            SnapshotAssertion::new(self.tool_calls.clone(), self.stdout.clone(), name);
+        match &self.working_dir {
+            Some(dir) => assertion.in_dir(dir),
+            None => assertion,
+        }
+    }
+
+    /// Assert the trace matches the stored snapshot `name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics with a line diff against the stored snapshot, or when it is missing
+    /// and `APTITUDE_UPDATE_SNAPSHOTS` is not set.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// expect(&output).to_match_snapshot("read-order");
+    /// ```
+    pub fn to_match_snapshot(&self, name: &str) {
+        self.snapshot(name).to_match();
+    }
+
+    fn tool_assertion(&self, selector: ToolSelector) -> ToolAssertion {
+        let assertion = ToolAssertion::new(self.tool_calls.clone(), selector);
+        match &self.working_dir {
+            Some(dir) => assertion.in_dir(dir),
+            None => assertion,
+        }
+    }
 }
 
 // Backward compatibility: keep ToolCallExpectation as an alias
@@ -144,27 +313,31 @@ pub type ToolCallExpectation = ExecutionExpectation;
 #[derive(Debug, Clone)]
 pub struct ToolAssertion {
     tool_calls: Vec<ToolCall>,
-    tool: Tool,
+    tool: ToolSelector,
     params: Option<HashMap<String, String>>,
+    matchers: Vec<SharedMatcher>,
     expected_count: Option<usize>,
     min_count: Option<usize>,
     max_count: Option<usize>,
-    after_tool: Option<Tool>,
-    before_tool: Option<Tool>,
+    after_tool: Option<ToolSelector>,
+    before_tool: Option<ToolSelector>,
+    working_dir: Option<PathBuf>,
 }
 
 impl ToolAssertion {
     /// Create a new tool assertion.
-    pub fn new(tool_calls: Vec<ToolCall>, tool: Tool) -> Self {
+    pub fn new(tool_calls: Vec<ToolCall>, tool: impl Into<ToolSelector>) -> Self {
         Self {
             tool_calls,
-            tool,
+            tool: tool.into(),
             params: None,
+            matchers: Vec::new(),
             expected_count: None,
             min_count: None,
             max_count: None,
             after_tool: None,
             before_tool: None,
+            working_dir: None,
         }
     }
 
@@ -191,6 +364,48 @@ impl ToolAssertion {
         self
     }
 
+    /// Only count calls that also satisfy a custom matcher.
+    ///
+    /// Accepts a closure or any [`CallMatcher`]. Can be repeated; every
+    /// matcher must pass. Count and ordering constraints apply to the calls
+    /// that match.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// expect(&tool_calls)
+    ///     .tool(Tool::Bash)
+    ///     .matching(|call: &ToolCall| {
+    ///         let command = call.params["command"].as_str().unwrap_or_default();
+    ///         command.starts_with("cargo ") && command.contains("--release")
+    ///     })
+    ///     .after(Tool::Edit)
+    ///     .to_be_called();
+    /// ```
+    pub fn matching(mut self, matcher: impl CallMatcher + 'static) -> Self {
+        self.matchers.push(SharedMatcher::new(matcher));
+        self
+    }
+
+    /// Also match path parameters relative to this directory.
+    ///
+    /// Set automatically from the session's working directory by [`expect`].
+    /// Absolute patterns keep matching the raw path.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// expect_tools(&tool_calls)
+    ///     .tool(Tool::Edit)
+    ///     .in_dir("/Users/alice/proj")
+    ///     .with_params(params! {"file_path" => r"^src/config\.json$"})
+    ///     .to_be_called();
+    /// ```
+    pub fn in_dir(mut self, working_dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(working_dir.into());
+        self
+    }
+
     /// Assert the tool was called exactly N times.
     ///
     /// # Example
@@ -246,8 +461,8 @@ impl ToolAssertion {
     ///     .after(Tool::Read)
     ///     .to_be_called();
     /// ```
-    pub fn after(mut self, tool: Tool) -> Self {
-        self.after_tool = Some(tool);
+    pub fn after(mut self, tool: impl Into<ToolSelector>) -> Self {
+        self.after_tool = Some(tool.into());
         self
     }
 
@@ -261,8 +476,8 @@ impl ToolAssertion {
     ///     .before(Tool::Write)
     ///     .to_be_called();
     /// ```
-    pub fn before(mut self, tool: Tool) -> Self {
-        self.before_tool = Some(tool);
+    pub fn before(mut self, tool: impl Into<ToolSelector>) -> Self {
+        self.before_tool = Some(tool.into());
         self
     }
 
@@ -338,24 +553,16 @@ impl ToolAssertion {
     ///
     /// Panics if the nth call doesn't exist.
     pub fn nth_call(&self, n: usize) -> NthCallAssertion {
-        let matching_calls: Vec<&ToolCall> = self
-            .tool_calls
-            .iter()
-            .filter(|c| c.name == self.tool.as_str())
-            .collect();
-
-        if n == 0 || n > matching_calls.len() {
-            panic!(
+        match self.try_nth_call(n) {
+            Ok(call) => call,
+            Err(_) => panic!(
                 "assertion failed: expected {} call #{} to exist\n\n  actual: {} calls made\n{}",
                 self.tool,
                 n,
-                matching_calls.len(),
+                self.calls_of_tool().len(),
                 self.format_tool_calls()
-            );
+            ),
         }
-
-        let call = matching_calls[n - 1];
-        NthCallAssertion::new(call.clone(), self.tool, n, self.tool_calls.clone())
     }
 
     /// Get the last call of this tool for further assertions.
@@ -373,29 +580,84 @@ impl ToolAssertion {
     ///
     /// Panics if the tool was never called.
     pub fn last_call(&self) -> NthCallAssertion {
-        let matching_calls: Vec<&ToolCall> = self
-            .tool_calls
-            .iter()
-            .filter(|c| c.name == self.tool.as_str())
-            .collect();
-
-        if matching_calls.is_empty() {
-            panic!(
+        match self.try_last_call() {
+            Ok(call) => call,
+            Err(_) => panic!(
                 "assertion failed: expected {} to have been called\n\n  actual: 0 calls made\n{}",
                 self.tool,
                 self.format_tool_calls()
-            );
+            ),
         }
-
-        let n = matching_calls.len();
-        let call = matching_calls[n - 1];
-        NthCallAssertion::new(call.clone(), self.tool, n, self.tool_calls.clone())
     }
 
     // =========================================================================
     // Non-panicking evaluation
     // =========================================================================
 
+    /// Like [`to_be_called`](Self::to_be_called), but returns an error instead of panicking.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// expect(&tool_calls).tool(Tool::Read).try_to_be_called()?;
+    /// ```
+    pub fn try_to_be_called(&self) -> Result<(), AssertionError> {
+        self.evaluate_called(true).try_to_pass()
+    }
+
+    /// Like [`not_to_be_called`](Self::not_to_be_called), but returns an error instead of panicking.
+    pub fn try_not_to_be_called(&self) -> Result<(), AssertionError> {
+        self.evaluate_called(false).try_to_pass()
+    }
+
+    /// Like [`nth_call`](Self::nth_call), but returns an error if the call doesn't exist.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let second = expect(&tool_calls).tool(Tool::Read).try_nth_call(2)?;
+    /// second.try_has_params(params! {"file_path" => "b.txt"})?;
+    /// ```
+    pub fn try_nth_call(&self, n: usize) -> Result<NthCallAssertion, AssertionError> {
+        let matching_calls = self.calls_of_tool();
+
+        if n == 0 || n > matching_calls.len() {
+            return Err(AssertionError::new(
+                format!("{} call #{} to exist", self.tool, n),
+                format!("only {} calls made", matching_calls.len()),
+            ));
+        }
+
+        let call = matching_calls[n - 1];
+        Ok(NthCallAssertion {
+            call: call.clone(),
+            tool: self.tool.clone(),
+            n,
+            all_calls: self.tool_calls.clone(),
+            working_dir: self.working_dir.clone(),
+        })
+    }
+
+    /// Like [`last_call`](Self::last_call), but returns an error if the tool was never called.
+    pub fn try_last_call(&self) -> Result<NthCallAssertion, AssertionError> {
+        let matching_calls = self.calls_of_tool();
+
+        let Some(call) = matching_calls.last() else {
+            return Err(AssertionError::new(
+                format!("{} to have been called", self.tool),
+                format!("tool '{}' was never called", self.tool),
+            ));
+        };
+
+        Ok(NthCallAssertion {
+            call: (*call).clone(),
+            tool: self.tool.clone(),
+            n: matching_calls.len(),
+            all_calls: self.tool_calls.clone(),
+            working_dir: self.working_dir.clone(),
+        })
+    }
+
     /// Evaluate the assertion without panicking (expects tool to be called).
     ///
     /// Returns an `AssertionResult` that can be inspected.
@@ -436,20 +698,31 @@ impl ToolAssertion {
     // Internal helpers
     // =========================================================================
 
+    /// Calls to the selected tool, ignoring parameter and custom constraints.
+    fn calls_of_tool(&self) -> Vec<&ToolCall> {
+        self.tool_calls
+            .iter()
+            .filter(|c| self.tool.matches(&c.name))
+            .collect()
+    }
+
     fn get_matching_calls(&self) -> Vec<&ToolCall> {
         self.tool_calls
             .iter()
-            .filter(|c| c.name == self.tool.as_str())
-            .filter(|c| {
-                if let Some(params) = &self.params {
-                    params_match(params, &c.params)
-                } else {
-                    true
-                }
-            })
+            .filter(|c| self.call_matches(c))
             .collect()
     }
 
+    /// Whether a call satisfies the tool, parameter and custom constraints.
+    fn call_matches(&self, call: &ToolCall) -> bool {
+        self.tool.matches(&call.name)
+            && self
+                .params
+                .as_ref()
+                .is_none_or(|params| call_params_match(params, call, self.working_dir.as_deref()))
+            && self.matchers.iter().all(|m| m.matches(call))
+    }
+
     fn evaluate_called(&self, should_be_called: bool) -> AssertionResult {
         let matching_calls = self.get_matching_calls();
         let count = matching_calls.len();
@@ -460,17 +733,21 @@ impl ToolAssertion {
 
         // Check called/not called first (fundamental constraint)
         if should_be_called && !was_called {
-            let param_desc = self
+            let mut param_desc = self
                 .params
                 .as_ref()
-                .map(|p| format!(" with params {:?}", p))
+                .map(|p| format!(" with params {{{}}}", format_params(p)))
                 .unwrap_or_default();
+            for matcher in &self.matchers {
+                param_desc.push_str(&format!(" matching {}", matcher.describe()));
+            }
             failures.push(format!("tool '{}'{} was never called", self.tool, param_desc));
         } else if !should_be_called && was_called {
             let found = matching_calls.first().unwrap();
             failures.push(format!(
-                "tool '{}' was called but should not have been. Found: {:?}",
-                self.tool, found.params
+                "tool '{}' was called but should not have been. Found: {}",
+                self.tool,
+                truncate(&found.params.to_string(), MAX_PARAMS_CHARS)
             ));
         }
 
@@ -511,20 +788,71 @@ impl ToolAssertion {
         if failures.is_empty() {
             AssertionResult::pass(description)
         } else {
-            AssertionResult::fail(description, failures.join("; "))
+            let mut reason = failures.join("; ");
+            if should_be_called && !was_called {
+                reason.push_str(&self.format_closest_calls());
+            }
+            AssertionResult::fail(description, reason)
         }
     }
 
+    /// Describe why the calls of this tool that came closest did not match.
+    ///
+    /// Calls are ranked by how many parameters and matchers they failed, and
+    /// each lists the failed keys with the pattern and the actual value.
+    fn format_closest_calls(&self) -> String {
+        if self.params.is_none() && self.matchers.is_empty() {
+            return String::new();
+        }
+
+        let mut candidates: Vec<(usize, &ToolCall, Vec<String>)> = self
+            .tool_calls
+            .iter()
+            .enumerate()
+            .filter(|(_, call)| self.tool.matches(&call.name))
+            .map(|(i, call)| {
+                let mut problems: Vec<String> = self
+                    .params
+                    .as_ref()
+                    .map(|p| param_mismatches(p, call, self.working_dir.as_deref()))
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|m| m.to_string())
+                    .collect();
+                for matcher in self.matchers.iter().filter(|m| !m.matches(call)) {
+                    problems.push(format!("does not match {}", matcher.describe()));
+                }
+                (i + 1, call, problems)
+            })
+            .collect();
+        if candidates.is_empty() {
+            return String::new();
+        }
+        candidates.sort_by_key(|(_, _, problems)| problems.len());
+
+        let mut output = String::from("\nclosest calls:");
+        for (n, call, problems) in candidates.iter().take(MAX_CLOSEST_CALLS) {
+            output.push_str(&format!("\n  call #{} ({}):", n, call.name));
+            for problem in problems {
+                output.push_str(&format!("\n    {}", problem));
+            }
+        }
+        if candidates.len() > MAX_CLOSEST_CALLS {
+            let more = candidates.len() - MAX_CLOSEST_CALLS;
+            output.push_str(&format!("\n  ... and {} more", more));
+        }
+        output
+    }
+
     /// Build a human-readable description of what this assertion checks.
     fn build_description(&self, should_be_called: bool) -> String {
         let mut parts = vec![self.tool.to_string()];
 
         if let Some(params) = &self.params {
-            let param_str: Vec<String> = params
-                .iter()
-                .map(|(k, v)| format!("{}='{}'", k, v))
-                .collect();
-            parts.push(format!("with {}", param_str.join(", ")));
+            parts.push(format!("with {}", format_params(params)));
+        }
+        for matcher in &self.matchers {
+            parts.push(format!("matching {}", matcher.describe()));
         }
 
         if should_be_called {
@@ -553,21 +881,15 @@ impl ToolAssertion {
     }
 
     /// Check if tool was called after another tool. Returns error message if failed.
-    fn check_after(&self, after_tool: &Tool) -> Option<String> {
+    fn check_after(&self, after_tool: &ToolSelector) -> Option<String> {
         let mut seen_after = false;
 
         for call in &self.tool_calls {
-            if call.name == after_tool.as_str() {
+            if after_tool.matches(&call.name) {
                 seen_after = true;
             }
-            if call.name == self.tool.as_str() && seen_after {
-                if let Some(params) = &self.params {
-                    if params_match(params, &call.params) {
-                        return None; // Success
-                    }
-                } else {
-                    return None; // Success
-                }
+            if seen_after && self.call_matches(call) {
+                return None; // Success
             }
         }
 
@@ -579,29 +901,23 @@ impl ToolAssertion {
     }
 
     /// Check if tool was called before another tool. Returns error message if failed.
-    fn check_before(&self, before_tool: &Tool) -> Option<String> {
+    fn check_before(&self, before_tool: &ToolSelector) -> Option<String> {
         let mut seen_this = false;
 
         for call in &self.tool_calls {
-            if call.name == self.tool.as_str() {
-                if let Some(params) = &self.params {
-                    if params_match(params, &call.params) {
-                        seen_this = true;
-                    }
-                } else {
-                    seen_this = true;
-                }
+            if self.call_matches(call) {
+                seen_this = true;
             }
-            if call.name == before_tool.as_str() && seen_this {
+            if before_tool.matches(&call.name) && seen_this {
                 return None; // Success
             }
         }
 
-        let this_called = self.tool_calls.iter().any(|c| c.name == self.tool.as_str());
+        let this_called = self.tool_calls.iter().any(|c| self.tool.matches(&c.name));
         let before_called = self
             .tool_calls
             .iter()
-            .any(|c| c.name == before_tool.as_str());
+            .any(|c| before_tool.matches(&c.name));
 
         if !this_called {
             Some(format!("'{}' was never called", self.tool))
@@ -623,34 +939,7 @@ impl ToolAssertion {
     }
 
     fn format_tool_calls(&self) -> String {
-        if self.tool_calls.is_empty() {
-            return "  tool calls made: (none)\n".to_string();
-        }
-
-        let mut output = format!("  tool calls made ({}):\n", self.tool_calls.len());
-        for (i, call) in self.tool_calls.iter().enumerate() {
-            let params_preview = call
-                .params
-                .get("file_path")
-                .or_else(|| call.params.get("command"))
-                .or_else(|| call.params.get("pattern"))
-                .and_then(|v| v.as_str())
-                .map(|s| {
-                    if s.len() > 50 {
-                        format!("{}...", &s[..47])
-                    } else {
-                        s.to_string()
-                    }
-                })
-                .unwrap_or_else(|| "...".to_string());
-            output.push_str(&format!(
-                "    {}. {} {{ {} }}\n",
-                i + 1,
-                call.name,
-                params_preview
-            ));
-        }
-        output
+        format_tool_calls(&self.tool_calls)
     }
 }
 
@@ -660,16 +949,13 @@ impl ToolAssertion {
 #[derive(Debug, Clone)]
 pub struct NthCallAssertion {
     call: ToolCall,
-    tool: Tool,
+    tool: ToolSelector,
     n: usize,
     all_calls: Vec<ToolCall>,
+    working_dir: Option<PathBuf>,
 }
 
 impl NthCallAssertion {
-    fn new(call: ToolCall, tool: Tool, n: usize, all_calls: Vec<ToolCall>) -> Self {
-        Self { call, tool, n, all_calls }
-    }
-
     /// Assert this specific call has the given parameters (panics on mismatch).
     ///
     /// # Example
@@ -685,15 +971,24 @@ impl NthCallAssertion {
     ///
     /// Panics if the parameters don't match.
     pub fn has_params(self, params: HashMap<String, String>) -> Self {
-        if !params_match(&params, &self.call.params) {
+        let result = self.evaluate_params(params);
+        if !result.passed {
             panic!(
-                "assertion failed: {} call #{} params did not match\n\n  expected: {:?}\n  actual: {:?}\n{}",
-                self.tool, self.n, params, self.call.params, self.format_tool_calls()
+                "assertion failed: expected {}\n\n  reason: {}\n{}",
+                result.description,
+                result.reason.unwrap_or_default().replace('\n', "\n  "),
+                self.format_tool_calls()
             );
         }
         self
     }
 
+    /// Like [`has_params`](Self::has_params), but returns an error instead of panicking.
+    pub fn try_has_params(self, params: HashMap<String, String>) -> Result<Self, AssertionError> {
+        self.evaluate_params(params).try_to_pass()?;
+        Ok(self)
+    }
+
     /// Evaluate parameter match without panicking.
     ///
     /// Returns an `AssertionResult` that can be inspected.
@@ -709,13 +1004,17 @@ impl NthCallAssertion {
     /// assert!(result.passed);
     /// ```
     pub fn evaluate_params(&self, params: HashMap<String, String>) -> AssertionResult {
-        if params_match(&params, &self.call.params) {
-            AssertionResult::pass(format!("{} call #{} params match", self.tool, self.n))
+        let description = format!("{} call #{} params match", self.tool, self.n);
+        let mismatches = param_mismatches(&params, &self.call, self.working_dir.as_deref());
+        if mismatches.is_empty() {
+            AssertionResult::pass(description)
         } else {
-            AssertionResult::fail(
-                format!("{} call #{} params match", self.tool, self.n),
-                format!("expected {:?}, got {:?}", params, self.call.params),
-            )
+            let mut reason =
+                format!("{} of {} params did not match:", mismatches.len(), params.len());
+            for mismatch in &mismatches {
+                reason.push_str(&format!("\n  {}", mismatch));
+            }
+            AssertionResult::fail(description, reason)
         }
     }
 
@@ -732,33 +1031,64 @@ impl NthCallAssertion {
     }
 
     fn format_tool_calls(&self) -> String {
-        if self.all_calls.is_empty() {
-            return "  tool calls made: (none)\n".to_string();
-        }
+        format_tool_calls(&self.all_calls)
+    }
+}
 
-        let mut output = format!("  tool calls made ({}):\n", self.all_calls.len());
-        for (i, call) in self.all_calls.iter().enumerate() {
-            let params_preview = call
-                .params
-                .get("file_path")
-                .or_else(|| call.params.get("command"))
-                .or_else(|| call.params.get("pattern"))
-                .and_then(|v| v.as_str())
-                .map(|s| {
-                    if s.len() > 50 {
-                        format!("{}...", &s[..47])
-                    } else {
-                        s.to_string()
-                    }
-                })
-                .unwrap_or_else(|| "...".to_string());
-            output.push_str(&format!(
-                "    {}. {} {{ {} }}\n",
-                i + 1,
-                call.name,
-                params_preview
-            ));
-        }
-        output
+/// Most closely matching calls listed when a tool was never called as expected.
+const MAX_CLOSEST_CALLS: usize = 3;
+
+/// Longest parameter object shown for an unexpected call.
+const MAX_PARAMS_CHARS: usize = 200;
+
+/// Longest parameter preview per call in the tool-call trace.
+const MAX_PREVIEW_CHARS: usize = 50;
+
+/// Shorten a string to at most `max` characters, marking the cut with `...`.
+///
+/// Counts characters rather than bytes, so it never splits a multi-byte
+/// character.
+pub(crate) fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_string()
+    } else {
+        let kept: String = s.chars().take(max.saturating_sub(3)).collect();
+        format!("{}...", kept)
+    }
+}
+
+/// Format expected parameters as `key='pattern'`, sorted by key.
+fn format_params(params: &HashMap<String, String>) -> String {
+    let mut param_str: Vec<String> = params
+        .iter()
+        .map(|(k, v)| format!("{}='{}'", k, v))
+        .collect();
+    param_str.sort();
+    param_str.join(", ")
+}
+
+/// Format the full tool-call trace for panic messages.
+pub(crate) fn format_tool_calls(tool_calls: &[ToolCall]) -> String {
+    if tool_calls.is_empty() {
+        return "  tool calls made: (none)\n".to_string();
+    }
+
+    let mut output = format!("  tool calls made ({}):\n", tool_calls.len());
+    for (i, call) in tool_calls.iter().enumerate() {
+        let params_preview = call
+            .params
+            .get("file_path")
+            .or_else(|| call.params.get("command"))
+            .or_else(|| call.params.get("pattern"))
+            .and_then(|v| v.as_str())
+            .map(|s| truncate(s, MAX_PREVIEW_CHARS))
+            .unwrap_or_else(|| "...".to_string());
+        output.push_str(&format!(
+            "    {}. {} {{ {} }}\n",
+            i + 1,
+            call.name,
+            params_preview
+        ));
     }
+    output
 }
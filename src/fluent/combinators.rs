@@ -0,0 +1,146 @@
+//! Boolean combinators over assertion results.
+//!
+//! Any builder that can `evaluate()` can be grouped:
+//! - `any_of()` - Passes if at least one branch passes
+//! - `all_of()` - Passes if every branch passes
+//! - `not()` - Passes if the branch fails
+//!
+//! Failure reasons list each failing branch with its own reason, indented
+//! under the group, so nested groups stay readable.
+
+use super::builder::AssertionResult;
+
+/// Pass if at least one of the results passed.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// any_of([
+///     expect(&output).tool(Tool::Bash).with_params(params! {"command" => "^npm test"}).evaluate(),
+///     expect(&output).tool(Tool::Bash).with_params(params! {"command" => "^pnpm test"}).evaluate(),
+/// ])
+/// .to_pass();
+/// ```
+pub fn any_of(results: impl IntoIterator<Item = AssertionResult>) -> AssertionResult {
+    let results: Vec<AssertionResult> = results.into_iter().collect();
+    let description = group_description("any of", &results);
+
+    if results.iter().any(|r| r.passed) {
+        AssertionResult::pass(description)
+    } else {
+        let reason = format!(
+            "none of {} branches passed{}",
+            results.len(),
+            branch_reasons(&results)
+        );
+        AssertionResult::fail(description, reason)
+    }
+}
+
+/// Pass if every result passed.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// all_of([
+///     expect(&output).tool(Tool::Read).evaluate(),
+///     expect(&output).stdout().contains("done").evaluate(),
+/// ])
+/// .to_pass();
+/// ```
+pub fn all_of(results: impl IntoIterator<Item = AssertionResult>) -> AssertionResult {
+    let results: Vec<AssertionResult> = results.into_iter().collect();
+    let description = group_description("all of", &results);
+    let failed: Vec<AssertionResult> = results.iter().filter(|r| !r.passed).cloned().collect();
+
+    if failed.is_empty() {
+        AssertionResult::pass(description)
+    } else {
+        let reason = format!(
+            "{} of {} branches failed{}",
+            failed.len(),
+            results.len(),
+            branch_reasons(&failed)
+        );
+        AssertionResult::fail(description, reason)
+    }
+}
+
+/// Pass if the result failed.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// not(expect(&output).sequence([Tool::Write, Tool::Read]).evaluate()).to_pass();
+/// ```
+pub fn not(result: AssertionResult) -> AssertionResult {
+    let description = format!("not ({})", result.description);
+
+    if result.passed {
+        AssertionResult::fail(description, format!("{} passed", result.description))
+    } else {
+        AssertionResult::pass(description)
+    }
+}
+
+fn group_description(label: &str, results: &[AssertionResult]) -> String {
+    let descriptions: Vec<&str> = results.iter().map(|r| r.description.as_str()).collect();
+    format!("{} [{}]", label, descriptions.join(", "))
+}
+
+/// One indented line per failed branch; nested reasons are indented further.
+pub(crate) fn branch_reasons(results: &[AssertionResult]) -> String {
+    let mut out = String::new();
+    for result in results.iter().filter(|r| !r.passed) {
+        let reason = result.reason.as_deref().unwrap_or("unknown reason");
+        out.push_str(&format!("\n  ✗ {}: ", result.description));
+        out.push_str(&reason.replace('\n', "\n  "));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pass(description: &str) -> AssertionResult {
+        AssertionResult::pass(description)
+    }
+
+    fn fail(description: &str, reason: &str) -> AssertionResult {
+        AssertionResult::fail(description, reason)
+    }
+
+    #[test]
+    fn test_any_of() {
+        assert!(any_of([fail("a", "x"), pass("b")]).passed);
+
+        let result = any_of([fail("a", "x"), fail("b", "y")]);
+        assert!(!result.passed);
+        assert_eq!(result.description, "any of [a, b]");
+        assert_eq!(
+            result.reason.unwrap(),
+            "none of 2 branches passed\n  ✗ a: x\n  ✗ b: y"
+        );
+    }
+
+    #[test]
+    fn test_all_of_nested() {
+        let result = all_of([pass("a"), any_of([fail("b", "x"), fail("c", "y")])]);
+        assert!(!result.passed);
+        assert_eq!(
+            result.reason.unwrap(),
+            "1 of 2 branches failed\n  ✗ any of [b, c]: none of 2 branches passed\n    ✗ b: x\n    ✗ c: y"
+        );
+    }
+
+    #[test]
+    fn test_not() {
+        assert!(not(fail("a", "x")).passed);
+
+        let result = not(pass("a"));
+        assert!(!result.passed);
+        assert_eq!(result.description, "not (a)");
+        assert_eq!(result.reason.unwrap(), "a passed");
+    }
+}
@@ -0,0 +1,49 @@
+//! Error type for the `try_` fluent methods.
+
+use super::builder::AssertionResult;
+
+/// A failed assertion, returned by the `try_` variants of fluent methods.
+///
+/// Lets tests use `?` instead of panicking, or collect failures with
+/// [`soft()`](super::soft).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("expected {description}: {reason}")]
+pub struct AssertionError {
+    /// Description of what was asserted.
+    pub description: String,
+    /// Why the assertion failed.
+    pub reason: String,
+}
+
+impl AssertionError {
+    pub(crate) fn new(description: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl AssertionResult {
+    /// Convert into a `Result`, failing with an [`AssertionError`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// expect(&tool_calls).tool(Tool::Read).evaluate().try_to_pass()?;
+    /// ```
+    pub fn try_to_pass(&self) -> Result<(), AssertionError> {
+        if self.passed {
+            Ok(())
+        } else {
+            let reason = self.reason.as_deref().unwrap_or("unknown reason");
+            Err(AssertionError::new(self.description.clone(), reason))
+        }
+    }
+}
+
+impl From<AssertionError> for AssertionResult {
+    fn from(error: AssertionError) -> Self {
+        AssertionResult::fail(error.description, error.reason)
+    }
+}
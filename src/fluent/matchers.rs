@@ -1,18 +1,41 @@
 //! Parameter matching utilities for tool call assertions.
 //!
 //! This module provides utilities for matching expected parameters against
-//! actual tool call parameters using regex patterns.
+//! actual tool call parameters.
+//!
+//! Keys are top-level parameter names or paths into nested input:
+//! - `edits[0].new_string` - index into an array
+//! - `edits[*].new_string` - any element matches (`[any]` also works)
+//! - `todos[all].status` - every element matches, and there is at least one
+//! - `questions.length` - length of an array, string, or object
+//!
+//! Values are regex patterns (falling back to exact match), except:
+//! - `> 3`, `>= 3`, `< 3`, `<= 3`, `== 3`, `!= 3` compare numerically
+//! - a plain number equals a numeric parameter exactly
+//! - `true`/`false` equal a boolean parameter exactly
 
 use regex::Regex;
+use serde_json::Value;
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::agents::mapping::canonical;
+use crate::parser::ToolCall;
+
+use super::builder::truncate;
+use super::paths::normalize_path;
 
 /// Match expected parameters against actual tool call parameters.
 ///
-/// Uses regex matching. If the pattern is not a valid regex, falls back to exact match.
+/// Keys may be paths into nested input, and values may be typed
+/// comparisons; see the [module documentation](self). Other values use
+/// regex matching, falling back to exact match if the pattern is not a
+/// valid regex.
 ///
 /// # Arguments
 ///
-/// * `expected` - Map of parameter names to expected regex patterns
+/// * `expected` - Map of parameter names or paths to expected patterns
 /// * `actual` - The actual JSON value containing the tool call parameters
 ///
 /// # Returns
@@ -31,33 +54,377 @@ use std::collections::HashMap;
 ///
 /// assert!(params_match(&expected, &json!({"file_path": "test.txt"})));
 /// assert!(!params_match(&expected, &json!({"file_path": "test.rs"})));
+///
+/// let mut nested = HashMap::new();
+/// nested.insert("edits[*].new_string".to_string(), "unwrap".to_string());
+/// nested.insert("edits.length".to_string(), ">= 2".to_string());
+///
+/// let input = json!({"edits": [{"new_string": "a"}, {"new_string": "b.unwrap()"}]});
+/// assert!(params_match(&nested, &input));
 /// ```
-pub fn params_match(expected: &HashMap<String, String>, actual: &serde_json::Value) -> bool {
-    for (key, pattern) in expected {
-        let actual_value = actual.get(key);
-
-        let actual_str = match actual_value {
-            Some(serde_json::Value::String(s)) => s.clone(),
-            Some(v) => v.to_string(),
-            None => return false,
-        };
+pub fn params_match(expected: &HashMap<String, String>, actual: &Value) -> bool {
+    expected
+        .iter()
+        .all(|(key, pattern)| param_matches(actual, key, pattern))
+}
+
+/// Match expected parameters against a specific tool call.
+///
+/// Like [`params_match`], but a key the call does not have is looked up
+/// under the names that tool uses for the same thing, so `file_path` also
+/// matches `notebook_path` on `NotebookEdit` and `path` on `Grep`/`Glob`/`LS`.
+///
+/// With a working directory, path parameters also match in their
+/// normalized form relative to it (`src/config.json`).
+pub(crate) fn call_params_match(
+    expected: &HashMap<String, String>,
+    call: &ToolCall,
+    working_dir: Option<&Path>,
+) -> bool {
+    expected
+        .iter()
+        .all(|(key, pattern)| call_key_matches(call, key, pattern, working_dir))
+}
+
+/// An expected parameter that a call did not satisfy.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ParamMismatch {
+    /// The expected key, as written in the assertion.
+    pub key: String,
+    /// The pattern the value had to match.
+    pub pattern: String,
+    /// The parameter that was compared and its value, or `None` if missing.
+    pub actual: Option<(String, Value)>,
+}
 
-        // Try regex, fall back to exact match if invalid
-        match Regex::new(pattern) {
-            Ok(re) => {
-                if !re.is_match(&actual_str) {
-                    return false;
+impl std::fmt::Display for ParamMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: expected '{}', ", self.key, self.pattern)?;
+        match &self.actual {
+            None => write!(f, "but the call has no '{}'", split_root(&self.key).0),
+            Some((name, value)) => {
+                let value = truncate(&value.to_string(), MAX_VALUE_CHARS);
+                if *name == self.key {
+                    write!(f, "got {}", value)
+                } else {
+                    write!(f, "got {} = {}", name, value)
                 }
             }
-            Err(_) => {
-                if &actual_str != pattern {
-                    return false;
-                }
+        }
+    }
+}
+
+/// Longest parameter value shown in a mismatch.
+const MAX_VALUE_CHARS: usize = 120;
+
+/// The expected parameters a call does not satisfy, sorted by key.
+pub(crate) fn param_mismatches(
+    expected: &HashMap<String, String>,
+    call: &ToolCall,
+    working_dir: Option<&Path>,
+) -> Vec<ParamMismatch> {
+    let mut mismatches: Vec<ParamMismatch> = expected
+        .iter()
+        .filter(|(key, pattern)| !call_key_matches(call, key, pattern, working_dir))
+        .map(|(key, pattern)| ParamMismatch {
+            key: key.clone(),
+            pattern: pattern.clone(),
+            actual: compared_value(call, key),
+        })
+        .collect();
+    mismatches.sort_by(|a, b| a.key.cmp(&b.key));
+    mismatches
+}
+
+/// Whether one expected key matches the call, directly or through an alias.
+fn call_key_matches(call: &ToolCall, key: &str, pattern: &str, working_dir: Option<&Path>) -> bool {
+    if call_param_matches(call, key, pattern, working_dir) {
+        return true;
+    }
+    let (root, rest) = split_root(key);
+    call.params.get(root).is_none()
+        && param_aliases(&call.name, root).iter().any(|alias| {
+            call_param_matches(call, &format!("{}{}", alias, rest), pattern, working_dir)
+        })
+}
+
+/// The parameter an expected key was compared against: the key itself, or
+/// the top-level parameter (or its alias) for nested paths.
+fn compared_value(call: &ToolCall, key: &str) -> Option<(String, Value)> {
+    if let Some(value) = call.params.get(key) {
+        return Some((key.to_string(), value.clone()));
+    }
+    let (root, _) = split_root(key);
+    std::iter::once(root)
+        .chain(param_aliases(&call.name, root).iter().copied())
+        .find_map(|name| Some((name.to_string(), call.params.get(name)?.clone())))
+}
+
+/// Parameters that hold a file or directory path.
+const PATH_PARAMS: &[&str] = &["file_path", "notebook_path", "path"];
+
+/// Match one key against a call, trying path parameters both raw and
+/// relative to the working directory.
+fn call_param_matches(
+    call: &ToolCall,
+    key: &str,
+    pattern: &str,
+    working_dir: Option<&Path>,
+) -> bool {
+    if param_matches(&call.params, key, pattern) {
+        return true;
+    }
+    let (Some(dir), Some(raw)) = (working_dir, call.params.get(key).and_then(Value::as_str)) else {
+        return false;
+    };
+    PATH_PARAMS.contains(&key)
+        && normalize_path(raw, dir)
+            .relative
+            .is_some_and(|relative| value_matches(pattern, &Value::String(relative)))
+}
+
+/// Other parameter names a tool uses for a common parameter.
+fn param_aliases(tool_name: &str, key: &str) -> &'static [&'static str] {
+    match (tool_name, key) {
+        (canonical::NOTEBOOK_EDIT, "file_path") => &["notebook_path"],
+        (canonical::GREP | canonical::GLOB | canonical::LIST_DIRECTORY, "file_path") => &["path"],
+        _ => &[],
+    }
+}
+
+/// Match one key against the parameters. A key that exists verbatim wins
+/// over its interpretation as a path.
+fn param_matches(params: &Value, key: &str, pattern: &str) -> bool {
+    if let Some(value) = params.get(key) {
+        return value_matches(pattern, value);
+    }
+    parse_path(key).is_some_and(|path| path_matches(params, &path, pattern))
+}
+
+/// Split a key into its top-level name and the rest of the path.
+fn split_root(key: &str) -> (&str, &str) {
+    let end = key.find(['.', '[']).unwrap_or(key.len());
+    key.split_at(end)
+}
+
+/// One step of a parameter path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+    Any,
+    All,
+}
+
+/// Parse `a.b[0].c[*]` into segments. Returns `None` for malformed paths.
+fn parse_path(key: &str) -> Option<Vec<Segment<'_>>> {
+    let mut segments = Vec::new();
+    let mut rest = key;
+
+    while !rest.is_empty() {
+        if let Some(bracketed) = rest.strip_prefix('[') {
+            let end = bracketed.find(']')?;
+            segments.push(match &bracketed[..end] {
+                "*" | "any" => Segment::Any,
+                "all" => Segment::All,
+                index => Segment::Index(index.parse().ok()?),
+            });
+            rest = &bracketed[end + 1..];
+        } else {
+            let name = if segments.is_empty() {
+                rest
+            } else {
+                rest.strip_prefix('.')?
+            };
+            let end = name.find(['.', '[']).unwrap_or(name.len());
+            if end == 0 {
+                return None;
             }
+            segments.push(Segment::Key(&name[..end]));
+            rest = &name[end..];
+        }
+    }
+
+    Some(segments)
+}
+
+fn path_matches(value: &Value, path: &[Segment<'_>], pattern: &str) -> bool {
+    let Some((first, rest)) = path.split_first() else {
+        return value_matches(pattern, value);
+    };
+
+    match first {
+        Segment::Key(key) => match value.get(*key) {
+            Some(v) => path_matches(v, rest, pattern),
+            None if *key == "length" => length_of(value)
+                .is_some_and(|n| path_matches(&Value::from(n), rest, pattern)),
+            None => false,
+        },
+        Segment::Index(i) => value.get(*i).is_some_and(|v| path_matches(v, rest, pattern)),
+        Segment::Any => value
+            .as_array()
+            .is_some_and(|items| items.iter().any(|v| path_matches(v, rest, pattern))),
+        // An empty array has nothing to vouch for the pattern, so it never matches
+        Segment::All => value.as_array().is_some_and(|items| {
+            !items.is_empty() && items.iter().all(|v| path_matches(v, rest, pattern))
+        }),
+    }
+}
+
+fn length_of(value: &Value) -> Option<usize> {
+    match value {
+        Value::Array(items) => Some(items.len()),
+        Value::String(s) => Some(s.chars().count()),
+        Value::Object(map) => Some(map.len()),
+        _ => None,
+    }
+}
+
+/// A numeric comparison written as a parameter value, e.g. `>= 2`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Gt(f64),
+    Ge(f64),
+    Lt(f64),
+    Le(f64),
+    Eq(f64),
+    Ne(f64),
+}
+
+impl Comparison {
+    fn parse(pattern: &str) -> Option<Self> {
+        let pattern = pattern.trim();
+        let (op, number) = [">=", "<=", "==", "!=", ">", "<"]
+            .iter()
+            .find_map(|op| Some((*op, pattern.strip_prefix(op)?)))?;
+        let n = number.trim().parse().ok()?;
+
+        Some(match op {
+            ">=" => Comparison::Ge(n),
+            "<=" => Comparison::Le(n),
+            "==" => Comparison::Eq(n),
+            "!=" => Comparison::Ne(n),
+            ">" => Comparison::Gt(n),
+            _ => Comparison::Lt(n),
+        })
+    }
+
+    fn holds(self, actual: f64) -> bool {
+        match self {
+            Comparison::Gt(n) => actual > n,
+            Comparison::Ge(n) => actual >= n,
+            Comparison::Lt(n) => actual < n,
+            Comparison::Le(n) => actual <= n,
+            Comparison::Eq(n) => actual == n,
+            Comparison::Ne(n) => actual != n,
         }
     }
+}
 
-    true
+/// A number, or a string that parses as one.
+fn numeric_value(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn value_matches(pattern: &str, actual: &Value) -> bool {
+    if let Some(comparison) = Comparison::parse(pattern) {
+        return numeric_value(actual).is_some_and(|n| comparison.holds(n));
+    }
+
+    match actual {
+        Value::Bool(b) if pattern == "true" || pattern == "false" => {
+            return b.to_string() == pattern;
+        }
+        Value::Number(n) => {
+            if let Ok(expected) = pattern.parse::<f64>() {
+                return n.as_f64() == Some(expected);
+            }
+        }
+        _ => {}
+    }
+
+    let actual_str = match actual {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    };
+
+    // Try regex, fall back to exact match if invalid
+    match Regex::new(pattern) {
+        Ok(re) => re.is_match(&actual_str),
+        Err(_) => actual_str == pattern,
+    }
+}
+
+/// A custom condition on a tool call.
+///
+/// Implemented for any `Fn(&ToolCall) -> bool` closure, so most tests can pass
+/// a closure to [`ToolAssertion::matching`](super::ToolAssertion::matching).
+/// Implement it directly to share a matcher with a readable description.
+///
+/// # Example
+///
+/// ```rust
+/// use aptitude::{CallMatcher, ToolCall};
+///
+/// struct CargoRelease;
+///
+/// impl CallMatcher for CargoRelease {
+///     fn matches(&self, call: &ToolCall) -> bool {
+///         let command = call.params["command"].as_str().unwrap_or_default();
+///         let mut words = command.split_whitespace();
+///         words.next() == Some("cargo") && words.any(|w| w == "--release")
+///     }
+///
+///     fn describe(&self) -> String {
+///         "cargo --release".to_string()
+///     }
+/// }
+/// ```
+pub trait CallMatcher: Send + Sync {
+    /// Whether the call satisfies this matcher.
+    fn matches(&self, call: &ToolCall) -> bool;
+
+    /// Short description used in assertion messages.
+    fn describe(&self) -> String {
+        "custom predicate".to_string()
+    }
+}
+
+impl<F> CallMatcher for F
+where
+    F: Fn(&ToolCall) -> bool + Send + Sync,
+{
+    fn matches(&self, call: &ToolCall) -> bool {
+        self(call)
+    }
+}
+
+/// Shared handle to a [`CallMatcher`], so assertion builders stay `Clone` and `Debug`.
+#[derive(Clone)]
+pub(crate) struct SharedMatcher(Arc<dyn CallMatcher>);
+
+impl SharedMatcher {
+    pub(crate) fn new(matcher: impl CallMatcher + 'static) -> Self {
+        Self(Arc::new(matcher))
+    }
+
+    pub(crate) fn matches(&self, call: &ToolCall) -> bool {
+        self.0.matches(call)
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        self.0.describe()
+    }
+}
+
+impl std::fmt::Debug for SharedMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SharedMatcher").field(&self.describe()).finish()
+    }
 }
 
 /// Create a parameter map from key-value pairs.
@@ -212,4 +579,141 @@ mod tests {
         assert_eq!(params.get("file_path"), Some(&"test.txt".to_string()));
         assert_eq!(params.get("content"), Some(&"hello".to_string()));
     }
+
+    #[test]
+    fn test_path_keys() {
+        let input = json!({
+            "edits": [
+                {"old_string": "a", "new_string": "b"},
+                {"old_string": "c", "new_string": "x.unwrap()"}
+            ],
+            "questions": [{"question": "Which database?"}]
+        });
+
+        assert!(params_match(&params! {"edits[*].new_string" => "unwrap"}, &input));
+        assert!(params_match(&params! {"edits[any].old_string" => "^c$"}, &input));
+        assert!(!params_match(&params! {"edits[all].new_string" => "unwrap"}, &input));
+        assert!(params_match(&params! {"edits[all].old_string" => "^[ac]$"}, &input));
+        assert!(params_match(&params! {"edits[1].new_string" => "unwrap"}, &input));
+        assert!(!params_match(&params! {"edits[2].new_string" => ".*"}, &input));
+        assert!(params_match(
+            &params! {"questions[0].question" => "database"},
+            &input
+        ));
+        assert!(params_match(&params! {"edits.length" => "2"}, &input));
+        assert!(!params_match(&params! {"edits[x].new_string" => ".*"}, &input));
+
+        // `[all]` needs at least one element
+        let empty = json!({"todos": []});
+        assert!(!params_match(
+            &params! {"todos[all].status" => "completed"},
+            &empty
+        ));
+        assert!(!params_match(
+            &params! {"todos[*].status" => "completed"},
+            &empty
+        ));
+    }
+
+    #[test]
+    fn test_verbatim_key_wins_over_path() {
+        let input = json!({"a.b": "flat", "a": {"b": "nested"}});
+        assert!(params_match(&params! {"a.b" => "^flat$"}, &input));
+    }
+
+    #[test]
+    fn test_typed_comparisons() {
+        let input = json!({"timeout": 5000, "limit": "20", "run_in_background": true});
+
+        assert!(params_match(&params! {"timeout" => "> 1000"}, &input));
+        assert!(params_match(&params! {"timeout" => "<=5000"}, &input));
+        assert!(!params_match(&params! {"timeout" => "< 5000"}, &input));
+        assert!(params_match(&params! {"timeout" => "!= 1"}, &input));
+        assert!(params_match(&params! {"limit" => ">= 20"}, &input));
+        assert!(params_match(&params! {"run_in_background" => "true"}, &input));
+        assert!(!params_match(&params! {"run_in_background" => "false"}, &input));
+        assert!(!params_match(&params! {"run_in_background" => "> 0"}, &input));
+
+        // Plain numbers compare numerically instead of as substrings
+        assert!(!params_match(&params! {"timeout" => "500"}, &input));
+        assert!(params_match(&params! {"timeout" => "5000.0"}, &input));
+    }
+
+    #[test]
+    fn test_call_params_aliases() {
+        let params = params! {"file_path" => r"\.ipynb$"};
+        let call = |name: &str, params| ToolCall {
+            name: name.to_string(),
+            params,
+            timestamp: chrono::Utc::now(),
+        };
+
+        assert!(call_params_match(
+            &params,
+            &call("NotebookEdit", json!({"notebook_path": "a.ipynb"})),
+            None
+        ));
+        assert!(call_params_match(
+            &params,
+            &call("Grep", json!({"path": "b.ipynb"})),
+            None
+        ));
+        assert!(!call_params_match(
+            &params,
+            &call("Read", json!({"notebook_path": "a.ipynb"})),
+            None
+        ));
+        assert!(call_params_match(
+            &params! {"file_path.length" => "> 3"},
+            &call("NotebookEdit", json!({"notebook_path": "a.ipynb"})),
+            None
+        ));
+    }
+
+    #[test]
+    fn test_call_params_relative_paths() {
+        let call = ToolCall {
+            name: "Edit".to_string(),
+            params: json!({"file_path": "/nonexistent/alice/proj/src/config.json"}),
+            timestamp: chrono::Utc::now(),
+        };
+        let dir = Some(Path::new("/nonexistent/alice/proj"));
+
+        let relative = params! {"file_path" => r"^src/config\.json$"};
+        assert!(call_params_match(&relative, &call, dir));
+        assert!(!call_params_match(&relative, &call, None));
+
+        // Raw matching still works with a working directory
+        let raw = params! {"file_path" => "^/nonexistent/alice/"};
+        assert!(call_params_match(&raw, &call, dir));
+        assert!(!call_params_match(&params! {"file_path" => "^/src"}, &call, dir));
+    }
+
+    #[test]
+    fn test_param_mismatches() {
+        let call = ToolCall {
+            name: "Grep".to_string(),
+            params: json!({"path": "/src", "pattern": "TODO", "-n": true}),
+            timestamp: chrono::Utc::now(),
+        };
+        let expected = params! {
+            "pattern" => "TODO",
+            "file_path" => "^/tests",
+            "-n" => "false",
+            "glob" => "*.rs",
+        };
+
+        let mismatches: Vec<String> = param_mismatches(&expected, &call, None)
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        assert_eq!(
+            mismatches,
+            [
+                "-n: expected 'false', got true",
+                "file_path: expected '^/tests', got path = \"/src\"",
+                "glob: expected '*.rs', but the call has no 'glob'",
+            ]
+        );
+    }
 }
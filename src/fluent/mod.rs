@@ -30,16 +30,36 @@
 //!     .evaluate();
 //! ```
 
+mod access;
 mod builder;
+mod combinators;
+mod error;
 mod matchers;
+mod paths;
+mod selector;
+mod sequence;
+mod shell;
+mod snapshot;
+mod soft;
 mod stdout;
 mod tool;
 
+pub use access::{
+    file_accesses, session_file_accesses, AccessMode, FileAccess, FileAccessAssertion,
+};
 pub use builder::{
     expect, expect_tools, AssertionResult, ExecutionExpectation, NthCallAssertion, ToolAssertion,
     ToolCallExpectation,
 };
-pub use matchers::params_match;
+pub use combinators::{all_of, any_of, not};
+pub use error::AssertionError;
+pub use matchers::{params_match, CallMatcher};
+pub use paths::{normalize_path, NormalizedPath};
+pub use selector::{ToolCategory, ToolSelector};
+pub use sequence::{SequenceAssertion, SequenceStep};
+pub use shell::{shell, Redirect, RedirectKind, ShellCommand, ShellMatcher};
+pub use snapshot::{render_trace, SnapshotAssertion, SNAPSHOT_DIR, UPDATE_SNAPSHOTS_ENV};
+pub use soft::{soft, SoftAssertions};
 pub use stdout::StdoutAssertion;
 pub use tool::Tool;
 
@@ -0,0 +1,161 @@
+//! Path normalization against the session's working directory.
+//!
+//! Agents report absolute paths (`/Users/alice/proj/src/config.json`), which
+//! makes patterns machine-specific. Normalizing against the working directory
+//! gives a relative form (`src/config.json`) that is stable across machines,
+//! and tells whether a path escapes the project.
+
+use std::path::{Component, Path, PathBuf};
+
+/// A tool-call path resolved against a working directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedPath {
+    /// Absolute path with `~`, `.`, `..` and symlinks resolved.
+    pub absolute: PathBuf,
+    /// Path relative to the working directory, or `None` if it lies outside.
+    pub relative: Option<String>,
+}
+
+impl NormalizedPath {
+    /// Whether the path escapes the working directory.
+    pub fn is_outside(&self) -> bool {
+        self.relative.is_none()
+    }
+}
+
+/// Resolve a path the way the agent's shell would see it.
+///
+/// `~` expands to `$HOME`, relative paths are joined onto `working_dir`, `.`
+/// and `..` are removed, and symlinks are resolved for the part of the path
+/// that exists on disk (so files created or deleted by the agent still
+/// normalize).
+///
+/// # Example
+///
+/// ```rust
+/// use aptitude::fluent::normalize_path;
+/// use std::path::Path;
+///
+/// let path = normalize_path("/proj/src/../Cargo.toml", Path::new("/proj"));
+/// assert_eq!(path.relative.as_deref(), Some("Cargo.toml"));
+///
+/// assert!(normalize_path("../other/secret.txt", Path::new("/proj")).is_outside());
+/// ```
+pub fn normalize_path(path: &str, working_dir: &Path) -> NormalizedPath {
+    let working_dir = resolve_symlinks(&lexical(working_dir));
+    let expanded = expand_home(path);
+    let joined = if expanded.is_relative() {
+        working_dir.join(expanded)
+    } else {
+        expanded
+    };
+    let absolute = resolve_symlinks(&lexical(&joined));
+
+    let relative = absolute.strip_prefix(&working_dir).ok().map(|rel| {
+        if rel.as_os_str().is_empty() {
+            ".".to_string()
+        } else {
+            rel.to_string_lossy().into_owned()
+        }
+    });
+
+    NormalizedPath { absolute, relative }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    let rest = match path.strip_prefix('~') {
+        Some("") => "",
+        Some(rest) if rest.starts_with('/') => &rest[1..],
+        _ => return PathBuf::from(path),
+    };
+    match std::env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Remove `.` and `..` components without touching the filesystem.
+fn lexical(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    out.push(component);
+                }
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Canonicalize the longest existing ancestor and re-append the rest.
+fn resolve_symlinks(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(canonical) = std::fs::canonicalize(existing) {
+            return missing
+                .iter()
+                .rev()
+                .fold(canonical, |acc: PathBuf, part| acc.join(part));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_and_outside() {
+        let dir = Path::new("/nonexistent/proj");
+        let inside = normalize_path("/nonexistent/proj/./src/lib.rs", dir);
+        assert_eq!(inside.relative.as_deref(), Some("src/lib.rs"));
+        assert_eq!(
+            normalize_path("src/../lib.rs", dir).relative.as_deref(),
+            Some("lib.rs")
+        );
+        assert_eq!(normalize_path(".", dir).relative.as_deref(), Some("."));
+
+        let escaped = normalize_path("../proj2/x", dir);
+        assert!(escaped.is_outside());
+        assert_eq!(escaped.absolute, PathBuf::from("/nonexistent/proj2/x"));
+        assert!(normalize_path("/etc/passwd", dir).is_outside());
+    }
+
+    #[test]
+    fn test_home_expansion() {
+        let Some(home) = std::env::var_os("HOME") else {
+            return;
+        };
+        let normalized = normalize_path("~/notes.txt", Path::new("/nonexistent"));
+        assert_eq!(
+            normalized.absolute,
+            resolve_symlinks(&PathBuf::from(home).join("notes.txt"))
+        );
+        assert!(normalized.is_outside());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks() {
+        let tmp = tempfile::tempdir().unwrap();
+        let real = tmp.path().join("real");
+        std::fs::create_dir(&real).unwrap();
+        std::os::unix::fs::symlink(&real, tmp.path().join("link")).unwrap();
+
+        let via_link = tmp.path().join("link/new.txt");
+        let normalized = normalize_path(&via_link.to_string_lossy(), &real);
+        assert_eq!(normalized.relative.as_deref(), Some("new.txt"));
+    }
+}
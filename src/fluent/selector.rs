@@ -0,0 +1,173 @@
+//! Tool categories and selectors for matching several tools at once.
+//!
+//! A [`ToolSelector`] decides which tool calls an assertion applies to: a
+//! single [`Tool`], any of a list of tools, or every member of a
+//! [`ToolCategory`].
+
+use super::Tool;
+
+/// A family of tools with similar effects.
+///
+/// Categories let guardrails cover every tool that can do the same thing,
+/// e.g. "no write-like tool touched `*.js`" regardless of whether the agent
+/// used `Write`, `Edit`, `MultiEdit` or `NotebookEdit`.
+///
+/// # Example
+///
+/// ```rust
+/// use aptitude::{Tool, ToolCategory};
+///
+/// assert!(ToolCategory::FileWrite.tools().contains(&Tool::NotebookEdit));
+/// assert_eq!(ToolCategory::from_name("shell"), Some(ToolCategory::Shell));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ToolCategory {
+    /// Tools that read file contents (`Read`, `Grep`)
+    FileRead,
+    /// Tools that create or modify files (`Write`, `Edit`, `MultiEdit`, `NotebookEdit`)
+    FileWrite,
+    /// Tools that run or manage shell commands (`Bash`, `BashOutput`, `KillShell`)
+    Shell,
+    /// Tools that reach the network (`WebFetch`, `WebSearch`)
+    Network,
+}
+
+impl ToolCategory {
+    /// Get the category name as used in YAML (e.g. `file_write`).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ToolCategory::FileRead => "file_read",
+            ToolCategory::FileWrite => "file_write",
+            ToolCategory::Shell => "shell",
+            ToolCategory::Network => "network",
+        }
+    }
+
+    /// Parse a category name (case-insensitive).
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "file_read" => Some(ToolCategory::FileRead),
+            "file_write" => Some(ToolCategory::FileWrite),
+            "shell" => Some(ToolCategory::Shell),
+            "network" => Some(ToolCategory::Network),
+            _ => None,
+        }
+    }
+
+    /// Get the tools that belong to this category.
+    pub fn tools(&self) -> &'static [Tool] {
+        match self {
+            ToolCategory::FileRead => &[Tool::Read, Tool::Grep],
+            ToolCategory::FileWrite => {
+                &[Tool::Write, Tool::Edit, Tool::MultiEdit, Tool::NotebookEdit]
+            }
+            ToolCategory::Shell => &[Tool::Bash, Tool::BashOutput, Tool::KillShell],
+            ToolCategory::Network => &[Tool::WebFetch, Tool::WebSearch],
+        }
+    }
+
+    /// Get all categories.
+    pub fn all() -> &'static [ToolCategory] {
+        &[
+            ToolCategory::FileRead,
+            ToolCategory::FileWrite,
+            ToolCategory::Shell,
+            ToolCategory::Network,
+        ]
+    }
+}
+
+impl std::fmt::Display for ToolCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Which tool calls an assertion applies to.
+///
+/// Anything that converts into a selector (a [`Tool`] or a [`ToolCategory`])
+/// can be passed where a selector is expected.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ToolSelector {
+    /// A single tool.
+    Tool(Tool),
+    /// Any of the listed tools.
+    AnyOf(Vec<Tool>),
+    /// Any tool in the category.
+    Category(ToolCategory),
+}
+
+impl ToolSelector {
+    /// Check whether a tool call name is selected.
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            ToolSelector::Tool(tool) => tool.matches(name),
+            ToolSelector::AnyOf(tools) => tools.iter().any(|t| t.matches(name)),
+            ToolSelector::Category(category) => category.tools().iter().any(|t| t.matches(name)),
+        }
+    }
+}
+
+impl From<Tool> for ToolSelector {
+    fn from(tool: Tool) -> Self {
+        ToolSelector::Tool(tool)
+    }
+}
+
+impl From<ToolCategory> for ToolSelector {
+    fn from(category: ToolCategory) -> Self {
+        ToolSelector::Category(category)
+    }
+}
+
+impl std::fmt::Display for ToolSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToolSelector::Tool(tool) => write!(f, "{}", tool),
+            ToolSelector::AnyOf(tools) => {
+                let names: Vec<&str> = tools.iter().map(|t| t.as_str()).collect();
+                write!(f, "{}", names.join("|"))
+            }
+            ToolSelector::Category(category) => write!(f, "{}", category),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_category_round_trip() {
+        for category in ToolCategory::all() {
+            assert_eq!(ToolCategory::from_name(category.as_str()), Some(*category));
+        }
+        assert_eq!(
+            ToolCategory::from_name("FILE_WRITE"),
+            Some(ToolCategory::FileWrite)
+        );
+        assert_eq!(ToolCategory::from_name("write"), None);
+    }
+
+    #[test]
+    fn test_selector_matches() {
+        let category = ToolSelector::from(ToolCategory::FileWrite);
+        assert!(category.matches("Write"));
+        assert!(category.matches("NotebookEdit"));
+        assert!(!category.matches("Read"));
+
+        let any = ToolSelector::AnyOf(vec![Tool::Grep, Tool::Glob]);
+        assert!(any.matches("Glob"));
+        assert!(!any.matches("Read"));
+    }
+
+    #[test]
+    fn test_selector_display() {
+        assert_eq!(ToolSelector::from(Tool::Read).to_string(), "Read");
+        assert_eq!(ToolSelector::from(ToolCategory::Shell).to_string(), "shell");
+        assert_eq!(
+            ToolSelector::AnyOf(vec![Tool::Write, Tool::Edit]).to_string(),
+            "Write|Edit"
+        );
+    }
+}
@@ -0,0 +1,402 @@
+//! Ordered sequence assertions over the whole tool-call trace.
+//!
+//! This module provides:
+//! - `SequenceStep` - One entry in an expected sequence (a call, any number of calls, or no call)
+//! - `SequenceAssertion` - Builder that checks the steps against the trace in order
+
+use crate::parser::ToolCall;
+use super::builder::{format_tool_calls, AssertionResult};
+use super::error::AssertionError;
+use super::matchers::{call_params_match, CallMatcher, SharedMatcher};
+use super::{Tool, ToolCategory, ToolSelector};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// How many calls a sequence step consumes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StepKind {
+    /// Exactly one matching call.
+    Once,
+    /// Zero or more matching calls.
+    Many,
+    /// No matching call until the next `Once` step (or the end of the trace).
+    Never,
+}
+
+/// One entry in an expected tool-call sequence.
+///
+/// # Example
+///
+/// ```rust
+/// use aptitude::{params, SequenceStep, Tool, ToolCategory};
+///
+/// let steps = [
+///     SequenceStep::call(Tool::Read).with_params(params! {"file_path" => "AGENTS.md"}),
+///     SequenceStep::many(ToolCategory::FileRead),
+///     SequenceStep::never(Tool::Read).with_params(params! {"file_path" => r"\.env$"}),
+///     SequenceStep::call(Tool::Write),
+/// ];
+/// assert_eq!(steps[2].to_string(), r"no Read with file_path='\.env$'");
+/// ```
+#[derive(Debug, Clone)]
+pub struct SequenceStep {
+    selector: ToolSelector,
+    params: Option<HashMap<String, String>>,
+    matchers: Vec<SharedMatcher>,
+    kind: StepKind,
+}
+
+impl SequenceStep {
+    /// Expect exactly one call to the tool at this point.
+    pub fn call(tool: impl Into<ToolSelector>) -> Self {
+        Self::with_kind(tool.into(), StepKind::Once)
+    }
+
+    /// Allow any number of calls (including none) to the tool at this point.
+    pub fn many(tool: impl Into<ToolSelector>) -> Self {
+        Self::with_kind(tool.into(), StepKind::Many)
+    }
+
+    /// Forbid calls to the tool between the previous and next `call` step.
+    ///
+    /// A `never` step at the start or end of the sequence covers the calls
+    /// before the first or after the last `call` step.
+    pub fn never(tool: impl Into<ToolSelector>) -> Self {
+        Self::with_kind(tool.into(), StepKind::Never)
+    }
+
+    /// Only calls whose parameters match these patterns count for this step.
+    pub fn with_params(mut self, params: HashMap<String, String>) -> Self {
+        self.params = Some(params);
+        self
+    }
+
+    /// Only calls that also satisfy this matcher count for this step.
+    pub fn matching(mut self, matcher: impl CallMatcher + 'static) -> Self {
+        self.matchers.push(SharedMatcher::new(matcher));
+        self
+    }
+
+    fn with_kind(selector: ToolSelector, kind: StepKind) -> Self {
+        Self {
+            selector,
+            params: None,
+            matchers: Vec::new(),
+            kind,
+        }
+    }
+
+    fn matches(&self, call: &ToolCall, working_dir: Option<&Path>) -> bool {
+        self.selector.matches(&call.name)
+            && self
+                .params
+                .as_ref()
+                .is_none_or(|params| call_params_match(params, call, working_dir))
+            && self.matchers.iter().all(|m| m.matches(call))
+    }
+}
+
+impl From<Tool> for SequenceStep {
+    fn from(tool: Tool) -> Self {
+        SequenceStep::call(tool)
+    }
+}
+
+impl From<ToolCategory> for SequenceStep {
+    fn from(category: ToolCategory) -> Self {
+        SequenceStep::call(category)
+    }
+}
+
+impl std::fmt::Display for SequenceStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            StepKind::Once => {}
+            StepKind::Many => write!(f, "any number of ")?,
+            StepKind::Never => write!(f, "no ")?,
+        }
+        write!(f, "{}", self.selector)?;
+
+        if let Some(params) = &self.params {
+            let mut param_str: Vec<String> = params
+                .iter()
+                .map(|(k, v)| format!("{}='{}'", k, v))
+                .collect();
+            param_str.sort();
+            write!(f, " with {}", param_str.join(", "))?;
+        }
+        for matcher in &self.matchers {
+            write!(f, " matching {}", matcher.describe())?;
+        }
+        Ok(())
+    }
+}
+
+/// Builder for assertions on an ordered sequence of tool calls.
+///
+/// By default unrelated calls may appear between steps. With
+/// [`strict`](Self::strict) the steps must match consecutive calls, and only
+/// [`SequenceStep::many`] steps can absorb extra calls.
+#[derive(Debug, Clone)]
+pub struct SequenceAssertion {
+    tool_calls: Vec<ToolCall>,
+    steps: Vec<SequenceStep>,
+    strict: bool,
+    working_dir: Option<PathBuf>,
+}
+
+impl SequenceAssertion {
+    /// Create a new sequence assertion.
+    pub fn new(tool_calls: Vec<ToolCall>, steps: Vec<SequenceStep>) -> Self {
+        Self {
+            tool_calls,
+            steps,
+            strict: false,
+            working_dir: None,
+        }
+    }
+
+    // =========================================================================
+    // Builder methods (chainable)
+    // =========================================================================
+
+    /// Require the steps to match adjacent calls, with no gaps in between.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// expect(&tool_calls)
+    ///     .sequence([Tool::Read, Tool::Edit])
+    ///     .strict()
+    ///     .to_match();
+    /// ```
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Also match path parameters relative to this directory.
+    pub fn in_dir(mut self, working_dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(working_dir.into());
+        self
+    }
+
+    // =========================================================================
+    // Assertion methods (panic on failure)
+    // =========================================================================
+
+    /// Assert the trace contains the sequence.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the furthest step reached and the full trace if the
+    /// sequence is not found.
+    pub fn to_match(&self) {
+        let result = self.evaluate();
+        if !result.passed {
+            let reason = result.reason.as_deref().unwrap_or("unknown reason");
+            panic!(
+                "assertion failed: expected {}\n\n  reason: {}\n{}",
+                result.description,
+                reason,
+                format_tool_calls(&self.tool_calls)
+            );
+        }
+    }
+
+    // =========================================================================
+    // Non-panicking evaluation
+    // =========================================================================
+
+    /// Evaluate the assertion without panicking.
+    pub fn evaluate(&self) -> AssertionResult {
+        let description = self.build_description();
+        let mut matcher = Matcher::new(&self.steps, &self.tool_calls, self.strict);
+        matcher.working_dir = self.working_dir.as_deref();
+
+        if matcher.find() {
+            AssertionResult::pass(description)
+        } else {
+            AssertionResult::fail(description, matcher.failure_reason())
+        }
+    }
+
+    /// Like [`to_match`](Self::to_match), but returns an error instead of panicking.
+    pub fn try_to_match(&self) -> Result<(), AssertionError> {
+        self.evaluate().try_to_pass()
+    }
+
+    // =========================================================================
+    // Internal helpers
+    // =========================================================================
+
+    fn build_description(&self) -> String {
+        let steps: Vec<String> = self.steps.iter().map(|s| s.to_string()).collect();
+        let mut description = format!("sequence [{}]", steps.join(" -> "));
+        if self.strict {
+            description.push_str(" (strict)");
+        }
+        description
+    }
+}
+
+/// Backtracking search for the steps in the trace.
+///
+/// State is `(step index, call index)`. Failed states are remembered, so the
+/// search is bounded by `steps * calls`.
+struct Matcher<'a> {
+    steps: &'a [SequenceStep],
+    calls: &'a [ToolCall],
+    strict: bool,
+    working_dir: Option<&'a Path>,
+    /// Segment of each position: the number of `Once` steps before it.
+    segments: Vec<usize>,
+    failed: HashSet<(usize, usize)>,
+    /// Furthest `(steps done, calls consumed)` reached by any branch.
+    furthest: (usize, usize),
+    /// First forbidden call seen: `(never step, call index)`.
+    violation: Option<(usize, usize)>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(steps: &'a [SequenceStep], calls: &'a [ToolCall], strict: bool) -> Self {
+        let mut segments = Vec::with_capacity(steps.len() + 1);
+        let mut segment = 0;
+        for step in steps {
+            segments.push(segment);
+            if step.kind == StepKind::Once {
+                segment += 1;
+            }
+        }
+        segments.push(segment);
+
+        Self {
+            steps,
+            calls,
+            strict,
+            working_dir: None,
+            segments,
+            failed: HashSet::new(),
+            furthest: (0, 0),
+            violation: None,
+        }
+    }
+
+    fn find(&mut self) -> bool {
+        if !self.strict {
+            return self.run(0, 0);
+        }
+
+        // Strict sequences may start anywhere; earlier calls only have to
+        // respect leading `never` steps.
+        for start in 0..=self.calls.len() {
+            if self.run(0, start) {
+                return true;
+            }
+            if !self.allowed(0, start) {
+                break;
+            }
+        }
+        false
+    }
+
+    fn run(&mut self, i: usize, j: usize) -> bool {
+        if self.failed.contains(&(i, j)) {
+            return false;
+        }
+        let found = self.step(i, j);
+        if !found {
+            self.failed.insert((i, j));
+        }
+        found
+    }
+
+    fn step(&mut self, i: usize, j: usize) -> bool {
+        let Some(step) = self.steps.get(i) else {
+            // Remaining calls only have to respect trailing `never` steps
+            return (j..self.calls.len()).all(|k| self.allowed(i, k));
+        };
+        let current = self.calls.get(j);
+
+        match step.kind {
+            StepKind::Never => self.run(i + 1, j),
+            StepKind::Once => {
+                if current.is_some_and(|c| step.matches(c, self.working_dir)) {
+                    self.advance(i + 1, j + 1);
+                    if self.run(i + 1, j + 1) {
+                        return true;
+                    }
+                }
+                !self.strict && self.allowed(i, j) && self.run(i, j + 1)
+            }
+            StepKind::Many => {
+                if self.run(i + 1, j) {
+                    return true;
+                }
+                let matched = current.is_some_and(|c| step.matches(c, self.working_dir));
+                if matched && self.allowed(i, j) {
+                    self.advance(i, j + 1);
+                    return self.run(i, j + 1);
+                }
+                false
+            }
+        }
+    }
+
+    /// Whether call `j` may be passed over at position `i`.
+    fn allowed(&mut self, i: usize, j: usize) -> bool {
+        let Some(call) = self.calls.get(j) else {
+            return false;
+        };
+        let segment = self.segments[i];
+        let forbidden = self.steps.iter().enumerate().find(|(k, s)| {
+            s.kind == StepKind::Never
+                && self.segments[*k] == segment
+                && s.matches(call, self.working_dir)
+        });
+
+        match forbidden {
+            Some((k, _)) => {
+                self.violation.get_or_insert((k, j));
+                false
+            }
+            None => true,
+        }
+    }
+
+    fn advance(&mut self, steps_done: usize, calls_done: usize) {
+        if steps_done > self.furthest.0 {
+            self.furthest = (steps_done, calls_done);
+        }
+    }
+
+    fn failure_reason(&self) -> String {
+        let (done, calls_done) = self.furthest;
+        let missing = self.steps[done..]
+            .iter()
+            .position(|s| s.kind != StepKind::Never)
+            .map(|offset| done + offset);
+
+        let mut reasons = Vec::new();
+        if let Some(k) = missing {
+            let mut reason = format!("step {} ({}) was not found", k + 1, self.steps[k]);
+            if calls_done > 0 {
+                reason.push_str(&format!(" after call #{}", calls_done));
+            }
+            reasons.push(reason);
+        }
+        if let Some((k, j)) = self.violation {
+            reasons.push(format!(
+                "call #{} ({}) matched step {} ({})",
+                j + 1,
+                self.calls[j].name,
+                k + 1,
+                self.steps[k]
+            ));
+        }
+        if reasons.is_empty() {
+            reasons.push("sequence not found".to_string());
+        }
+        reasons.join("; ")
+    }
+}
@@ -0,0 +1,1157 @@
+//! Semantic parsing of shell commands for `Bash` assertions.
+//!
+//! This module provides:
+//! - `ShellCommand` - One simple command (program, arguments and redirections)
+//! - `ShellMatcher` - A [`CallMatcher`] over the `command` parameter, built with `shell()`
+//!
+//! Commands are split on `&&`, `||`, `;`, `|`, `&` and newlines. Command
+//! substitutions (`$(...)`, backticks), `bash -c '...'` and `eval` are parsed
+//! recursively, so `cat $(echo .env)` and `sh -c "cat .env"` both yield a
+//! `cat .env` command. Quoting and escaping follow POSIX shell rules; variable
+//! expansion and globbing are not performed.
+
+use std::iter::Peekable;
+
+use crate::parser::ToolCall;
+
+use super::matchers::CallMatcher;
+
+/// Programs whose positional arguments are files they read.
+const READERS: &[&str] = &[
+    "cat",
+    "tac",
+    "less",
+    "more",
+    "most",
+    "head",
+    "tail",
+    "bat",
+    "nl",
+    "wc",
+    "sort",
+    "uniq",
+    "cut",
+    "strings",
+    "xxd",
+    "od",
+    "hexdump",
+    "base64",
+    "md5sum",
+    "sha1sum",
+    "sha256sum",
+    "diff",
+    "cmp",
+    "file",
+    "source",
+    ".",
+];
+
+/// Programs whose first positional argument is a pattern or script, followed by files.
+const SEARCHERS: &[&str] = &["grep", "egrep", "fgrep", "rg", "ag", "sed", "awk", "jq"];
+
+/// Programs that run the rest of their arguments as another command.
+const WRAPPERS: &[&str] = &[
+    "sudo", "env", "nohup", "exec", "command", "nice", "time", "timeout", "xargs",
+];
+
+/// Flags of a wrapper that take a value, as short flag letters and long flags.
+///
+/// Values are skipped with their flag, so `sudo -u alice cat .env` runs
+/// `cat`, not `alice`.
+fn wrapper_value_flags(wrapper: &str) -> (&'static str, &'static [&'static str]) {
+    match wrapper {
+        "sudo" => (
+            "CDghpRTUu",
+            &[
+                "--close-from",
+                "--chdir",
+                "--group",
+                "--host",
+                "--prompt",
+                "--chroot",
+                "--command-timeout",
+                "--other-user",
+                "--user",
+            ],
+        ),
+        "env" => ("Cu", &["--chdir", "--unset"]),
+        "exec" => ("a", &[]),
+        "nice" => ("n", &["--adjustment"]),
+        "time" => ("fo", &["--format", "--output"]),
+        "timeout" => ("ks", &["--kill-after", "--signal"]),
+        "xargs" => (
+            "adEILnPs",
+            &[
+                "--arg-file",
+                "--delimiter",
+                "--max-lines",
+                "--max-args",
+                "--max-procs",
+                "--max-chars",
+                "--process-slot-var",
+            ],
+        ),
+        _ => ("", &[]),
+    }
+}
+
+/// Shell keywords that may precede a command.
+const KEYWORDS: &[&str] = &[
+    "if", "then", "else", "elif", "do", "while", "until", "!", "{",
+];
+
+/// Shell keywords that end a compound command and are not commands themselves.
+const CLOSERS: &[&str] = &["fi", "done", "esac", "}", "for", "case", "in"];
+
+/// Programs whose first positional argument is a script they run.
+const INTERPRETERS: &[&str] = &[
+    "sh", "bash", "zsh", "dash", "ksh", "python", "python3", "node", "ruby", "perl", "php", "deno",
+    "bun", "tsx", "ts-node",
+];
+
+/// Shells whose `-c` argument is itself a command line.
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "dash", "ksh"];
+
+/// Flags that take a value, per program: short flag letters and long flags.
+///
+/// A short flag's value is either the rest of its argument (`-n5`) or the
+/// next argument (`-n 5`); a long flag's is the next argument unless given
+/// with `=`. Values are not operands, so `head -n 5 .env` reads only `.env`.
+fn value_flags(program: &str) -> (&'static str, &'static [&'static str]) {
+    match program {
+        "head" => ("cn", &["--bytes", "--lines"]),
+        "tail" => ("cns", &["--bytes", "--lines", "--sleep-interval", "--pid"]),
+        "grep" | "egrep" | "fgrep" => (
+            "ABCDdefm",
+            &[
+                "--after-context",
+                "--before-context",
+                "--context",
+                "--devices",
+                "--directories",
+                "--regexp",
+                "--file",
+                "--max-count",
+                "--include",
+                "--exclude",
+                "--exclude-dir",
+                "--label",
+            ],
+        ),
+        "rg" => (
+            "ABCEMTefgjmt",
+            &[
+                "--after-context",
+                "--before-context",
+                "--context",
+                "--encoding",
+                "--max-columns",
+                "--type-not",
+                "--regexp",
+                "--file",
+                "--glob",
+                "--threads",
+                "--max-count",
+                "--type",
+            ],
+        ),
+        "sed" => ("efl", &["--expression", "--file", "--line-length"]),
+        "awk" => ("Ffv", &["--field-separator", "--file", "--assign"]),
+        "jq" => ("f", &["--from-file", "--indent"]),
+        "cut" => (
+            "bcdf",
+            &[
+                "--bytes",
+                "--characters",
+                "--delimiter",
+                "--fields",
+                "--output-delimiter",
+            ],
+        ),
+        "sort" => (
+            "STkot",
+            &[
+                "--buffer-size",
+                "--temporary-directory",
+                "--key",
+                "--output",
+                "--field-separator",
+            ],
+        ),
+        "uniq" => ("fsw", &["--skip-fields", "--skip-chars", "--check-chars"]),
+        _ => ("", &[]),
+    }
+}
+
+/// Long flags of searchers that give the pattern or script (as do `-e` and `-f`).
+const SCRIPT_FLAGS: &[&str] = &["--regexp", "--expression", "--file", "--from-file"];
+
+/// Kind of redirection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectKind {
+    /// `< file`
+    Input,
+    /// `> file`, `2> file`, `&> file`, `>| file`
+    Output,
+    /// `>> file`, `&>> file`
+    Append,
+}
+
+/// A redirection to or from a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    /// Direction of the redirection.
+    pub kind: RedirectKind,
+    /// File being redirected to or from.
+    pub target: String,
+}
+
+/// A simple command: one program with its arguments and redirections.
+///
+/// # Example
+///
+/// ```rust
+/// use aptitude::fluent::ShellCommand;
+///
+/// let commands = ShellCommand::parse("cd src && grep -rn TODO . | tee \"todo list.txt\"");
+/// assert_eq!(commands.len(), 3);
+/// assert_eq!(commands[1].program, "grep");
+/// assert!(commands[1].has_flag("-nr"));
+/// assert_eq!(commands[2].writes_files(), vec!["todo list.txt"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellCommand {
+    /// Program name, without its directory (`/bin/rm` becomes `rm`).
+    pub program: String,
+    /// Program as written, when it included a directory (`./build.sh`).
+    pub program_path: Option<String>,
+    /// Arguments after the program, with quotes removed.
+    pub args: Vec<String>,
+    /// File redirections attached to the command.
+    pub redirects: Vec<Redirect>,
+}
+
+impl ShellCommand {
+    /// Parse a command line into every simple command it runs.
+    ///
+    /// Commands from substitutions and nested shells follow the command that
+    /// contains them. Parsing never fails; unterminated quotes run to the end
+    /// of the input.
+    pub fn parse(command: &str) -> Vec<ShellCommand> {
+        let mut commands = Vec::new();
+        parse_into(command, &mut commands, 0);
+        commands
+    }
+
+    /// Files this command reads, from input redirections and the arguments of
+    /// well-known readers such as `cat`, `head`, `grep` or `source`.
+    pub fn reads_files(&self) -> Vec<&str> {
+        let mut files: Vec<&str> = self
+            .redirects
+            .iter()
+            .filter(|r| r.kind == RedirectKind::Input)
+            .map(|r| r.target.as_str())
+            .collect();
+
+        let positional = self.positional();
+        let program = self.program.as_str();
+        if READERS.contains(&program) {
+            files.extend(positional);
+        } else if SEARCHERS.contains(&program) {
+            files.extend(self.script_operands());
+        } else if matches!(program, "cp" | "install" | "scp" | "rsync") {
+            if let Some((_, sources)) = positional.split_last() {
+                files.extend_from_slice(sources);
+            }
+        }
+        files
+    }
+
+    /// Files this command writes, from output redirections and the arguments
+    /// of `tee`, `touch`, `truncate`, `sed -i`, `dd of=` and the destination of
+    /// `cp`/`mv`/`install`/`ln`. Devices under `/dev/` are ignored.
+    pub fn writes_files(&self) -> Vec<&str> {
+        let mut files: Vec<&str> = self
+            .redirects
+            .iter()
+            .filter(|r| r.kind != RedirectKind::Input)
+            .map(|r| r.target.as_str())
+            .collect();
+
+        let positional = self.positional();
+        match self.program.as_str() {
+            "tee" | "touch" | "truncate" => files.extend(positional),
+            "cp" | "mv" | "install" | "ln" | "scp" | "rsync" => files.extend(positional.last()),
+            "sed"
+                if self
+                    .args
+                    .iter()
+                    .any(|a| a.starts_with("-i") || a == "--in-place") =>
+            {
+                files.extend(self.script_operands())
+            }
+            "dd" => files.extend(self.args.iter().filter_map(|a| a.strip_prefix("of="))),
+            _ => {}
+        }
+        files.retain(|f| !f.starts_with("/dev/"));
+        files
+    }
+
+    /// Files this command executes: a program given by path, the script
+    /// passed to `source` or an interpreter such as `bash`, `python` or `node`.
+    pub fn executes_files(&self) -> Vec<&str> {
+        let mut files: Vec<&str> = self.program_path.iter().map(String::as_str).collect();
+
+        let program = self.program.as_str();
+        // Code given inline (`bash -c`, `python -m`, `node -e`) runs no script file
+        let inline_flags: &[&str] = if SHELLS.contains(&program) {
+            &["-c"]
+        } else {
+            &["-c", "-e", "-m", "-p"]
+        };
+        let inline = inline_flags.iter().any(|f| self.has_flag(f));
+        if matches!(program, "source" | ".") || (INTERPRETERS.contains(&program) && !inline) {
+            files.extend(self.positional().first());
+        }
+        files
+    }
+
+    /// Whether the command was given a flag.
+    ///
+    /// Short flags may be combined in any order, so `-rf` is present in
+    /// `rm -fr`, `rm -r -f` and `rm -rfv`. Long flags must appear verbatim,
+    /// optionally with an `=value`.
+    pub fn has_flag(&self, flag: &str) -> bool {
+        let flags = self.args.iter().take_while(|a| a.as_str() != "--");
+
+        if let Some(long) = flag.strip_prefix("--") {
+            return flags.filter_map(|a| a.strip_prefix("--")).any(|a| {
+                a == long
+                    || a.strip_prefix(long)
+                        .is_some_and(|rest| rest.starts_with('='))
+            });
+        }
+
+        let Some(short) = flag.strip_prefix('-') else {
+            return self.args.iter().any(|a| a == flag);
+        };
+        let given: String = flags
+            .filter(|a| a.starts_with('-') && !a.starts_with("--"))
+            .flat_map(|a| a.chars().skip(1))
+            .collect();
+        !short.is_empty() && short.chars().all(|c| given.contains(c))
+    }
+
+    /// Arguments that are neither flags nor flag values (everything after `--` counts).
+    fn positional(&self) -> Vec<&str> {
+        self.operands().0
+    }
+
+    /// Positional arguments after the pattern or script of a searcher such as
+    /// `grep` or `sed`. With `-e`/`-f` the script is the flag's value and
+    /// every positional is a file.
+    fn script_operands(&self) -> Vec<&str> {
+        let (operands, script_given) = self.operands();
+        let skip = if script_given { 0 } else { 1 };
+        operands.into_iter().skip(skip).collect()
+    }
+
+    /// Positional arguments, and whether a pattern or script was given as a flag value.
+    fn operands(&self) -> (Vec<&str>, bool) {
+        let (short, long) = value_flags(&self.program);
+        let mut operands = Vec::new();
+        let mut script_given = false;
+        let mut args = self.args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                operands.extend(args.map(String::as_str));
+                break;
+            }
+            if !arg.starts_with('-') || arg == "-" {
+                operands.push(arg.as_str());
+                continue;
+            }
+
+            let script = if arg.starts_with("--") {
+                let (name, attached) = match arg.split_once('=') {
+                    Some((name, _)) => (name, true),
+                    None => (arg.as_str(), false),
+                };
+                let takes_value = long.contains(&name);
+                if takes_value && !attached {
+                    args.next();
+                }
+                takes_value && SCRIPT_FLAGS.contains(&name)
+            } else {
+                // The first value flag takes the rest of the argument or the next one
+                let letters = &arg[1..];
+                let found = letters.char_indices().find_map(|(i, c)| {
+                    // `sed -i` takes an optional backup suffix in the same argument
+                    if self.program == "sed" && c == 'i' {
+                        return Some(None);
+                    }
+                    short.contains(c).then_some(Some((i, c)))
+                });
+                match found.flatten() {
+                    Some((i, c)) => {
+                        if i + c.len_utf8() == letters.len() {
+                            args.next();
+                        }
+                        matches!(c, 'e' | 'f')
+                    }
+                    None => false,
+                }
+            };
+            script_given |= script;
+        }
+        (operands, script_given)
+    }
+}
+
+/// Matcher over the `command` parameter of shell tool calls.
+///
+/// A call matches when at least one of its simple commands satisfies every
+/// constraint. File patterns are globs matched against the path and against
+/// its file name, so `*.env` matches `.env`, `config/.env` and
+/// `/home/me/app/.env`.
+///
+/// # Example
+///
+/// ```rust
+/// use aptitude::{expect_tools, shell, Tool, ToolCall};
+/// use serde_json::json;
+///
+/// let calls = vec![ToolCall {
+///     name: "Bash".to_string(),
+///     params: json!({"command": "cd app && head -n 5 'config/.env' | grep KEY"}),
+///     timestamp: chrono::Utc::now(),
+/// }];
+///
+/// expect_tools(&calls).tool(Tool::Bash).matching(shell().reads_file("*.env")).to_be_called();
+/// expect_tools(&calls)
+///     .tool(Tool::Bash)
+///     .matching(shell().program("rm").flags(["-rf"]))
+///     .not_to_be_called();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ShellMatcher {
+    program: Option<String>,
+    flags: Vec<String>,
+    reads_file: Option<String>,
+    writes_file: Option<String>,
+}
+
+/// Start a [`ShellMatcher`] that matches any shell command.
+pub fn shell() -> ShellMatcher {
+    ShellMatcher::default()
+}
+
+impl ShellMatcher {
+    /// Only match commands running this program (`rm`, `git`, ...).
+    pub fn program(mut self, program: impl Into<String>) -> Self {
+        self.program = Some(program.into());
+        self
+    }
+
+    /// Only match commands given all of these flags; see [`ShellCommand::has_flag`].
+    pub fn flags<I, S>(mut self, flags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.flags.extend(flags.into_iter().map(Into::into));
+        self
+    }
+
+    /// Only match commands reading a file that matches the glob.
+    pub fn reads_file(mut self, pattern: impl Into<String>) -> Self {
+        self.reads_file = Some(pattern.into());
+        self
+    }
+
+    /// Only match commands writing a file that matches the glob.
+    pub fn writes_file(mut self, pattern: impl Into<String>) -> Self {
+        self.writes_file = Some(pattern.into());
+        self
+    }
+
+    /// Whether any simple command in the command line satisfies the matcher.
+    pub fn matches_command(&self, command: &str) -> bool {
+        ShellCommand::parse(command)
+            .iter()
+            .any(|c| self.matches_simple(c))
+    }
+
+    fn matches_simple(&self, command: &ShellCommand) -> bool {
+        self.program.as_ref().is_none_or(|p| &command.program == p)
+            && self.flags.iter().all(|f| command.has_flag(f))
+            && self
+                .reads_file
+                .as_ref()
+                .is_none_or(|p| command.reads_files().iter().any(|f| path_matches(p, f)))
+            && self
+                .writes_file
+                .as_ref()
+                .is_none_or(|p| command.writes_files().iter().any(|f| path_matches(p, f)))
+    }
+}
+
+impl CallMatcher for ShellMatcher {
+    fn matches(&self, call: &ToolCall) -> bool {
+        call.params
+            .get("command")
+            .and_then(|c| c.as_str())
+            .is_some_and(|c| self.matches_command(c))
+    }
+
+    fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        match (&self.program, self.flags.is_empty()) {
+            (Some(program), true) => parts.push(format!("runs '{}'", program)),
+            (Some(program), false) => {
+                parts.push(format!("runs '{} {}'", program, self.flags.join(" ")))
+            }
+            (None, false) => parts.push(format!("uses {}", self.flags.join(" "))),
+            (None, true) => {}
+        }
+        if let Some(pattern) = &self.reads_file {
+            parts.push(format!("reads '{}'", pattern));
+        }
+        if let Some(pattern) = &self.writes_file {
+            parts.push(format!("writes '{}'", pattern));
+        }
+        if parts.is_empty() {
+            "any command".to_string()
+        } else {
+            format!("command that {}", parts.join(", "))
+        }
+    }
+}
+
+/// Match a glob against a path or its file name; invalid globs match exactly.
+pub(crate) fn path_matches(pattern: &str, path: &str) -> bool {
+    let name = basename(path);
+    match glob::Pattern::new(pattern) {
+        Ok(pat) => pat.matches(path) || pat.matches(name),
+        Err(_) => pattern == path || pattern == name,
+    }
+}
+
+// =========================================================================
+// Internal: lexing and command assembly
+// =========================================================================
+
+/// Limit on nested substitutions and `sh -c` strings.
+const MAX_DEPTH: usize = 8;
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Redirect(RedirectKind),
+    /// `<<`/`<<<` and fd duplications: the next word is not a file.
+    Skip,
+    Separator,
+}
+
+fn parse_into(command: &str, commands: &mut Vec<ShellCommand>, depth: usize) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let mut lexer = Lexer::new(command);
+    lexer.run();
+
+    let mut words = Vec::new();
+    let mut redirects = Vec::new();
+    let mut tokens = lexer.tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Word(word) => words.push(word),
+            Token::Redirect(kind) => {
+                if let Some(Token::Word(target)) = tokens.next_if(|t| matches!(t, Token::Word(_))) {
+                    redirects.push(Redirect { kind, target });
+                }
+            }
+            Token::Skip => {
+                tokens.next_if(|t| matches!(t, Token::Word(_)));
+            }
+            Token::Separator => {
+                finish_command(
+                    std::mem::take(&mut words),
+                    std::mem::take(&mut redirects),
+                    commands,
+                    depth,
+                );
+            }
+        }
+    }
+    finish_command(words, redirects, commands, depth);
+
+    for inner in lexer.substitutions {
+        parse_into(&inner, commands, depth + 1);
+    }
+}
+
+fn finish_command(
+    words: Vec<String>,
+    redirects: Vec<Redirect>,
+    commands: &mut Vec<ShellCommand>,
+    depth: usize,
+) {
+    let mut words = words.into_iter().peekable();
+
+    // Skip keywords, assignments and wrappers (with their flags)
+    while let Some(word) = words.peek() {
+        let is_assignment = word
+            .split_once('=')
+            .is_some_and(|(name, _)| is_identifier(name));
+        if KEYWORDS.contains(&word.as_str()) || is_assignment {
+            words.next();
+        } else if WRAPPERS.contains(&basename(word)) {
+            let wrapper = basename(word).to_string();
+            words.next();
+            skip_wrapper_flags(&wrapper, &mut words);
+        } else {
+            break;
+        }
+    }
+
+    // A closer's redirections apply to the whole compound command, as in
+    // `while read l; do ...; done < .env`
+    let first = words.next().filter(|w| !CLOSERS.contains(&w.as_str()));
+    let Some(first) = first else {
+        if !redirects.is_empty() {
+            commands.push(ShellCommand {
+                program: String::new(),
+                program_path: None,
+                args: Vec::new(),
+                redirects,
+            });
+        }
+        return;
+    };
+    let program = basename(&first).to_string();
+    let args: Vec<String> = words.collect();
+
+    let nested = if SHELLS.contains(&program.as_str()) {
+        args.iter()
+            .position(|a| a.starts_with('-') && !a.starts_with("--") && a.ends_with('c'))
+            .and_then(|i| args.get(i + 1))
+            .cloned()
+    } else if program == "eval" {
+        Some(args.join(" "))
+    } else {
+        None
+    };
+
+    let program_path = first.contains('/').then_some(first.clone());
+    commands.push(ShellCommand {
+        program,
+        program_path,
+        args,
+        redirects,
+    });
+    if let Some(nested) = nested {
+        parse_into(&nested, commands, depth + 1);
+    }
+}
+
+/// Skip a wrapper's flags and their values, up to the command it runs.
+fn skip_wrapper_flags(wrapper: &str, words: &mut Peekable<impl Iterator<Item = String>>) {
+    let (short, long) = wrapper_value_flags(wrapper);
+    while let Some(flag) = words.next_if(|w| w.starts_with('-')) {
+        if flag == "--" {
+            break;
+        }
+        let takes_value = if flag.starts_with("--") {
+            long.contains(&flag.as_str())
+        } else {
+            // A value flag takes the rest of the argument, or the next one when last
+            let letters = &flag[1..];
+            letters
+                .char_indices()
+                .find(|(_, c)| short.contains(*c))
+                .is_some_and(|(i, c)| i + c.len_utf8() == letters.len())
+        };
+        if takes_value {
+            words.next();
+        }
+    }
+    // `timeout` takes a duration before the command
+    if wrapper == "timeout" {
+        words.next();
+    }
+}
+
+fn basename(word: &str) -> &str {
+    word.rsplit('/').next().unwrap_or(word)
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Splits a command line into words, operators and redirections.
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    tokens: Vec<Token>,
+    /// Inner text of `$(...)` and backtick substitutions.
+    substitutions: Vec<String>,
+    /// Here-document delimiters whose bodies start at the next newline.
+    heredocs: Vec<String>,
+    word: Option<String>,
+}
+
+impl Lexer {
+    fn new(input: &str) -> Self {
+        Self {
+            chars: input.chars().collect(),
+            pos: 0,
+            tokens: Vec::new(),
+            substitutions: Vec::new(),
+            heredocs: Vec::new(),
+            word: None,
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn push_char(&mut self, c: char) {
+        self.word.get_or_insert_with(String::new).push(c);
+    }
+
+    fn end_word(&mut self) {
+        if let Some(word) = self.word.take() {
+            self.tokens.push(Token::Word(word));
+        }
+    }
+
+    fn run(&mut self) {
+        while let Some(c) = self.peek(0) {
+            self.pos += 1;
+            match c {
+                ' ' | '\t' => self.end_word(),
+                '\n' => {
+                    self.end_word();
+                    self.tokens.push(Token::Separator);
+                    self.skip_heredoc_bodies();
+                }
+                '#' if self.word.is_none() => {
+                    while self.peek(0).is_some_and(|c| c != '\n') {
+                        self.pos += 1;
+                    }
+                }
+                '\'' => {
+                    let word = self.word.get_or_insert_with(String::new);
+                    while let Some(c) = self.chars.get(self.pos).copied() {
+                        self.pos += 1;
+                        if c == '\'' {
+                            break;
+                        }
+                        word.push(c);
+                    }
+                }
+                '"' => self.double_quoted(),
+                '\\' => match self.peek(0) {
+                    Some('\n') => self.pos += 1,
+                    Some(c) => {
+                        self.pos += 1;
+                        self.push_char(c);
+                    }
+                    None => {}
+                },
+                '$' if self.peek(0) == Some('(') => self.substitution(),
+                '`' => self.backticks(),
+                ';' | '&' | '|' | '(' | ')' => self.operator(c),
+                '<' | '>' => self.redirect(c),
+                _ => self.push_char(c),
+            }
+        }
+        self.end_word();
+    }
+
+    fn double_quoted(&mut self) {
+        self.word.get_or_insert_with(String::new);
+        while let Some(c) = self.peek(0) {
+            self.pos += 1;
+            match c {
+                '"' => return,
+                '\\' => match self.peek(0) {
+                    Some(next @ ('"' | '\\' | '$' | '`')) => {
+                        self.pos += 1;
+                        self.push_char(next);
+                    }
+                    Some('\n') => self.pos += 1,
+                    _ => self.push_char('\\'),
+                },
+                '$' if self.peek(0) == Some('(') => self.substitution(),
+                '`' => self.backticks(),
+                _ => self.push_char(c),
+            }
+        }
+    }
+
+    /// Read a `$(...)` substitution; the opening `$` is already consumed.
+    fn substitution(&mut self) {
+        let start = self.pos;
+        self.pos += 1;
+        let mut depth = 1;
+        let mut quote = None;
+        while let Some(c) = self.peek(0) {
+            self.pos += 1;
+            match (quote, c) {
+                (_, '\\') => self.pos += 1,
+                (None, '\'' | '"') => quote = Some(c),
+                (Some(q), _) if c == q => quote = None,
+                (None, '(') => depth += 1,
+                (None, ')') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let end = if depth == 0 { self.pos - 1 } else { self.pos };
+        let inner: String = self.chars[start + 1..end.min(self.chars.len())]
+            .iter()
+            .collect();
+
+        // The word keeps the raw text; the commands inside are parsed later
+        let raw = format!("$({})", inner);
+        self.word.get_or_insert_with(String::new).push_str(&raw);
+        self.substitutions.push(inner);
+    }
+
+    /// Read a backtick substitution; the opening backtick is already consumed.
+    fn backticks(&mut self) {
+        let mut inner = String::new();
+        while let Some(c) = self.peek(0) {
+            self.pos += 1;
+            match c {
+                '`' => break,
+                '\\' if self.peek(0) == Some('`') => {
+                    self.pos += 1;
+                    inner.push('`');
+                }
+                _ => inner.push(c),
+            }
+        }
+        let raw = format!("`{}`", inner);
+        self.word.get_or_insert_with(String::new).push_str(&raw);
+        self.substitutions.push(inner);
+    }
+
+    fn operator(&mut self, c: char) {
+        if c == '&' && self.peek(0) == Some('>') {
+            self.pos += 1;
+            self.end_word();
+            self.output_redirect();
+            return;
+        }
+        self.end_word();
+        // Consume the second character of `&&`, `||`, `;;` and `|&`
+        if matches!(
+            (c, self.peek(0)),
+            ('&', Some('&')) | ('|', Some('|' | '&')) | (';', Some(';'))
+        ) {
+            self.pos += 1;
+        }
+        self.tokens.push(Token::Separator);
+    }
+
+    fn redirect(&mut self, c: char) {
+        // A word of digits right before the operator is a file descriptor
+        if self
+            .word
+            .as_ref()
+            .is_some_and(|w| !w.is_empty() && w.chars().all(|c| c.is_ascii_digit()))
+        {
+            self.word = None;
+        }
+        self.end_word();
+
+        if c == '>' {
+            self.output_redirect();
+            return;
+        }
+        match (self.peek(0), self.peek(1)) {
+            (Some('<'), Some('<')) => {
+                self.pos += 2;
+                self.tokens.push(Token::Skip);
+            }
+            (Some('<'), _) => {
+                self.pos += 1;
+                if self.peek(0) == Some('-') {
+                    self.pos += 1;
+                }
+                self.heredoc_delimiter();
+            }
+            (Some('&'), _) => {
+                self.pos += 1;
+                self.tokens.push(Token::Skip);
+            }
+            _ => self.tokens.push(Token::Redirect(RedirectKind::Input)),
+        }
+    }
+
+    /// Emit an output redirection; the first `>` is already consumed.
+    fn output_redirect(&mut self) {
+        match self.peek(0) {
+            Some('>') => {
+                self.pos += 1;
+                self.tokens.push(Token::Redirect(RedirectKind::Append));
+            }
+            Some('&') => {
+                // `>&2` duplicates a descriptor
+                self.pos += 1;
+                self.tokens.push(Token::Skip);
+            }
+            Some('|') => {
+                self.pos += 1;
+                self.tokens.push(Token::Redirect(RedirectKind::Output));
+            }
+            _ => self.tokens.push(Token::Redirect(RedirectKind::Output)),
+        }
+    }
+
+    /// Read the delimiter word after `<<` and remember it for the next newline.
+    fn heredoc_delimiter(&mut self) {
+        while self.peek(0).is_some_and(|c| c == ' ' || c == '\t') {
+            self.pos += 1;
+        }
+        let mut delimiter = String::new();
+        while let Some(c) = self.peek(0) {
+            if c.is_whitespace() || matches!(c, ';' | '&' | '|' | '<' | '>' | '(' | ')') {
+                break;
+            }
+            self.pos += 1;
+            if !matches!(c, '\'' | '"' | '\\') {
+                delimiter.push(c);
+            }
+        }
+        self.heredocs.push(delimiter);
+    }
+
+    /// Skip here-document bodies; the preceding newline is already consumed.
+    fn skip_heredoc_bodies(&mut self) {
+        for delimiter in std::mem::take(&mut self.heredocs) {
+            loop {
+                let start = self.pos;
+                while self.peek(0).is_some_and(|c| c != '\n') {
+                    self.pos += 1;
+                }
+                let line: String = self.chars[start..self.pos].iter().collect();
+                if self.peek(0).is_some() {
+                    self.pos += 1;
+                }
+                if line.trim() == delimiter || self.peek(0).is_none() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(command: &str) -> Vec<ShellCommand> {
+        ShellCommand::parse(command)
+    }
+
+    fn programs(command: &str) -> Vec<String> {
+        parse(command).into_iter().map(|c| c.program).collect()
+    }
+
+    #[test]
+    fn test_splits_lists_and_pipelines() {
+        assert_eq!(
+            programs("cd src && ls -la | grep foo; echo done || true & wait"),
+            vec!["cd", "ls", "grep", "echo", "true", "wait"]
+        );
+        assert_eq!(programs("if [ -f x ]; then rm x; fi"), vec!["[", "rm"]);
+        assert_eq!(
+            programs("FOO=1 sudo -E /usr/bin/env BAR=2 make"),
+            vec!["make"]
+        );
+    }
+
+    #[test]
+    fn test_quoting() {
+        let commands = parse(r#"echo 'a b' "c \"d\" $HOME" e\ f"#);
+        assert_eq!(commands[0].args, vec!["a b", "c \"d\" $HOME", "e f"]);
+
+        let commands = parse("grep ';' file.txt");
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].reads_files(), vec!["file.txt"]);
+    }
+
+    #[test]
+    fn test_substitution_and_nested_shells() {
+        assert_eq!(
+            programs("echo $(cat .env | head -1)"),
+            vec!["echo", "cat", "head"]
+        );
+        assert_eq!(programs("echo \"`whoami`\""), vec!["echo", "whoami"]);
+        assert_eq!(
+            programs("bash -lc 'cat .env && rm -rf /'"),
+            vec!["bash", "cat", "rm"]
+        );
+        assert_eq!(programs("eval \"less secrets.txt\""), vec!["eval", "less"]);
+    }
+
+    #[test]
+    fn test_redirects() {
+        let commands = parse("sort < in.txt 2>/dev/null > out.txt 2>&1 >> log.txt");
+        assert_eq!(commands[0].reads_files(), vec!["in.txt"]);
+        assert_eq!(commands[0].writes_files(), vec!["out.txt", "log.txt"]);
+        assert!(commands[0].args.is_empty());
+
+        let commands = parse("echo hi &>all.log");
+        assert_eq!(commands[0].writes_files(), vec!["all.log"]);
+
+        let commands = parse("cat <<'EOF' > src/app.js\nconst a = 1; rm -rf /\nEOF\nls");
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0].writes_files(), vec!["src/app.js"]);
+        assert_eq!(commands[1].program, "ls");
+
+        assert!(parse("grep x <<< \"$input\"")[0].reads_files().is_empty());
+    }
+
+    #[test]
+    fn test_compound_command_redirects() {
+        let commands = parse("while read l; do echo $l; done < .env");
+        assert_eq!(commands.last().unwrap().reads_files(), vec![".env"]);
+
+        let commands = parse("for f in *.rs; do cat $f; done > all.txt");
+        assert_eq!(commands.last().unwrap().writes_files(), vec!["all.txt"]);
+        assert_eq!(
+            programs("if true; then ls; fi 2>/dev/null"),
+            vec!["true", "ls", ""]
+        );
+    }
+
+    #[test]
+    fn test_wrapper_flag_values() {
+        for command in [
+            "sudo -u alice cat .env",
+            "sudo -g wheel -u alice cat .env",
+            "sudo --user alice cat .env",
+            "nice -n 5 cat .env",
+            "nice -n5 cat .env",
+            "timeout 5 cat .env",
+            "timeout -s KILL 5 cat .env",
+            "timeout -k 2 --signal=TERM 5 cat .env",
+            "env -u HOME cat .env",
+            "xargs -n 1 cat .env",
+            "exec -a name cat .env",
+            "time -o t.txt cat .env",
+        ] {
+            let commands = parse(command);
+            assert_eq!(commands[0].program, "cat", "{}", command);
+            assert_eq!(commands[0].reads_files(), vec![".env"], "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_file_roles() {
+        assert_eq!(parse("head -n 5 .env")[0].reads_files(), vec![".env"]);
+        assert_eq!(
+            parse("tail -n20 -f log.txt")[0].reads_files(),
+            vec!["log.txt"]
+        );
+        assert_eq!(parse("grep -e KEY .env")[0].reads_files(), vec![".env"]);
+        assert_eq!(
+            parse("grep -A 3 -i KEY .env")[0].reads_files(),
+            vec![".env"]
+        );
+        assert_eq!(
+            parse("grep -rf patterns.txt --include '*.rs' src")[0].reads_files(),
+            vec!["src"]
+        );
+        assert_eq!(parse("sed -n 1p .env")[0].reads_files(), vec![".env"]);
+        assert_eq!(
+            parse("sed -i.bak -e 's/a/b/' x.js")[0].writes_files(),
+            vec!["x.js"]
+        );
+        assert_eq!(parse("cut -d = -f 2 .env")[0].reads_files(), vec![".env"]);
+        assert_eq!(
+            parse("awk -F : '{print $1}' /etc/passwd")[0].reads_files(),
+            vec!["/etc/passwd"]
+        );
+        assert_eq!(
+            parse("cp a.txt b.txt dir/")[0].reads_files(),
+            vec!["a.txt", "b.txt"]
+        );
+        assert_eq!(parse("cp a.txt b.txt dir/")[0].writes_files(), vec!["dir/"]);
+        assert_eq!(
+            parse("sed -i 's/a/b/' x.js")[0].writes_files(),
+            vec!["x.js"]
+        );
+        assert!(parse("sed 's/a/b/' x.js")[0].writes_files().is_empty());
+        assert_eq!(parse("dd if=a of=b.img")[0].writes_files(), vec!["b.img"]);
+        assert_eq!(
+            parse("echo x | tee -a out.log")[1].writes_files(),
+            vec!["out.log"]
+        );
+    }
+
+    #[test]
+    fn test_flags() {
+        let command = &parse("rm -f -r --no-preserve-root --verbose=2 -- -x")[0];
+        assert!(command.has_flag("-rf"));
+        assert!(command.has_flag("-r"));
+        assert!(command.has_flag("--no-preserve-root"));
+        assert!(command.has_flag("--verbose"));
+        assert!(!command.has_flag("-x"));
+        assert!(!command.has_flag("--force"));
+        assert!(!parse("rm -r x")[0].has_flag("-rf"));
+    }
+
+    #[test]
+    fn test_executes() {
+        assert_eq!(
+            parse("./scripts/deploy.sh --prod")[0].executes_files(),
+            vec!["./scripts/deploy.sh"]
+        );
+        assert_eq!(parse("source .env")[0].executes_files(), vec![".env"]);
+        assert_eq!(
+            parse("python3 -u tools/gen.py out")[0].executes_files(),
+            vec!["tools/gen.py"]
+        );
+        assert!(parse("python3 -c 'print(1)'")[0]
+            .executes_files()
+            .is_empty());
+        assert_eq!(parse("bash -ex ci.sh")[0].executes_files(), vec!["ci.sh"]);
+        assert!(parse("ls -la")[0].executes_files().is_empty());
+    }
+
+    #[test]
+    fn test_matcher() {
+        let env = shell().reads_file("*.env");
+        assert!(env.matches_command("cat .env"));
+        assert!(env.matches_command("cd app && tail -f config/prod.env | less"));
+        assert!(env.matches_command("sh -c 'cat < ~/.env'"));
+        assert!(!env.matches_command("echo .env"));
+        assert!(!env.matches_command("cat .envrc"));
+
+        let rm = shell().program("rm").flags(["-rf"]);
+        assert!(rm.matches_command("cd / && rm -fr tmp"));
+        assert!(!rm.matches_command("rm tmp && ls -rf"));
+        assert_eq!(rm.describe(), "command that runs 'rm -rf'");
+
+        let js = shell().writes_file("*.js");
+        assert!(js.matches_command("echo 1 > dist/app.js"));
+        assert!(js.matches_command("cat a | tee b.js > /dev/null"));
+        assert!(!js.matches_command("cat app.js"));
+    }
+}
@@ -0,0 +1,446 @@
+//! Snapshot testing of tool-call traces.
+//!
+//! A snapshot stores a normalized, line-per-call rendering of a trace under
+//! `__snapshots__/<name>.snap`. Runs compare against it and report a line
+//! diff. Set `APTITUDE_UPDATE_SNAPSHOTS=1` (or pass `--update-snapshots` to
+//! `aptitude run`) to record a new snapshot or accept a changed trace.
+
+use std::path::{Path, PathBuf};
+
+use super::builder::AssertionResult;
+use super::error::AssertionError;
+use super::paths::normalize_path;
+use crate::parser::ToolCall;
+
+/// Environment variable that makes snapshot assertions overwrite stored traces.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "APTITUDE_UPDATE_SNAPSHOTS";
+
+/// Separator between the calls and stdout in a stored snapshot.
+const STDOUT_HEADER: &str = "\n--- stdout ---\n";
+
+/// Directory snapshots are stored in, relative to the test.
+pub const SNAPSHOT_DIR: &str = "__snapshots__";
+
+/// Parameters recorded in a snapshot, in output order.
+///
+/// Contents of writes and edits are left out so a snapshot captures what the
+/// agent did, not every byte it produced.
+const KEY_PARAMS: &[&str] = &[
+    "file_path",
+    "notebook_path",
+    "path",
+    "pattern",
+    "command",
+    "url",
+    "query",
+    "subagent_type",
+    "skill",
+];
+
+/// Parameters holding paths, rendered relative to the working directory.
+const PATH_PARAMS: &[&str] = &["file_path", "notebook_path", "path"];
+
+/// Render a trace in the stored snapshot format.
+///
+/// Each call becomes one line with its tool name and key parameters. Paths
+/// inside the working directory are made relative, and the working directory
+/// prefix is stripped from shell commands, so the snapshot is stable across
+/// machines.
+///
+/// # Example
+///
+/// ```rust
+/// use aptitude::fluent::render_trace;
+/// use aptitude::ToolCall;
+/// use chrono::Utc;
+/// use serde_json::json;
+/// use std::path::Path;
+///
+/// let calls = vec![ToolCall {
+///     name: "Read".to_string(),
+///     params: json!({"file_path": "/proj/AGENTS.md"}),
+///     timestamp: Utc::now(),
+/// }];
+/// let trace = render_trace(&calls, None, Some(Path::new("/proj")));
+/// assert_eq!(trace, "1. Read file_path=\"AGENTS.md\"\n");
+/// ```
+pub fn render_trace(
+    tool_calls: &[ToolCall],
+    stdout: Option<&str>,
+    working_dir: Option<&Path>,
+) -> String {
+    let mut out = String::new();
+    for (i, call) in tool_calls.iter().enumerate() {
+        out.push_str(&format!("{}. {}", i + 1, call.name));
+        for key in KEY_PARAMS {
+            if let Some(value) = call.params.get(*key) {
+                out.push(' ');
+                out.push_str(key);
+                out.push('=');
+                out.push_str(&render_value(key, value, working_dir));
+            }
+        }
+        out.push('\n');
+    }
+    if let Some(stdout) = stdout {
+        out.push_str(STDOUT_HEADER);
+        let stdout = stdout.trim_end();
+        if !stdout.is_empty() {
+            out.push_str(stdout);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn render_value(key: &str, value: &serde_json::Value, working_dir: Option<&Path>) -> String {
+    let (Some(text), Some(dir)) = (value.as_str(), working_dir) else {
+        return value.to_string();
+    };
+    let text = if PATH_PARAMS.contains(&key) {
+        normalize_path(text, dir)
+            .relative
+            .unwrap_or_else(|| text.to_string())
+    } else if key == "command" {
+        text.replace(&format!("{}/", dir.display()), "")
+    } else {
+        text.to_string()
+    };
+    serde_json::Value::String(text).to_string()
+}
+
+/// Builder for comparing a trace against a stored snapshot.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// expect(&output)
+///     .snapshot("read-order")
+///     .with_stdout()
+///     .to_match();
+/// ```
+#[derive(Debug, Clone)]
+pub struct SnapshotAssertion {
+    tool_calls: Vec<ToolCall>,
+    stdout: Option<String>,
+    name: String,
+    dir: PathBuf,
+    working_dir: Option<PathBuf>,
+    include_stdout: bool,
+    keep_stdout: bool,
+    update: bool,
+}
+
+impl SnapshotAssertion {
+    /// Create a snapshot assertion stored as `__snapshots__/<name>.snap`.
+    ///
+    /// Updating is enabled when `APTITUDE_UPDATE_SNAPSHOTS` is set to
+    /// anything but `0`.
+    pub fn new(tool_calls: Vec<ToolCall>, stdout: Option<String>, name: impl Into<String>) -> Self {
+        let update = std::env::var(UPDATE_SNAPSHOTS_ENV).is_ok_and(|v| !v.is_empty() && v != "0");
+        Self {
+            tool_calls,
+            stdout,
+            name: name.into(),
+            dir: PathBuf::from(SNAPSHOT_DIR),
+            working_dir: None,
+            include_stdout: false,
+            keep_stdout: false,
+            update,
+        }
+    }
+
+    // =========================================================================
+    // Builder methods (chainable)
+    // =========================================================================
+
+    /// Store the snapshot in this directory instead of `./__snapshots__`.
+    pub fn snapshot_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = dir.into();
+        self
+    }
+
+    /// Render paths relative to this directory.
+    pub fn in_dir(mut self, working_dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(working_dir.into());
+        self
+    }
+
+    /// Include stdout in the snapshot.
+    pub fn with_stdout(mut self) -> Self {
+        self.include_stdout = true;
+        self
+    }
+
+    /// Compare only the calls, keeping the stored stdout as it is.
+    ///
+    /// For traces whose stdout is not known, such as one read from a session log.
+    pub fn keep_stdout(mut self) -> Self {
+        self.keep_stdout = true;
+        self
+    }
+
+    /// Record or overwrite the stored snapshot instead of comparing against it.
+    pub fn update(mut self, update: bool) -> Self {
+        self.update = update;
+        self
+    }
+
+    // =========================================================================
+    // Assertion methods (panic on failure)
+    // =========================================================================
+
+    /// Assert the trace matches the stored snapshot.
+    ///
+    /// A missing snapshot fails unless updating is enabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics with a line diff against the stored snapshot.
+    pub fn to_match(&self) {
+        let result = self.evaluate();
+        if !result.passed {
+            panic!(
+                "assertion failed: expected {}\n\n  reason: {}\n",
+                result.description,
+                result.reason.unwrap_or_default()
+            );
+        }
+    }
+
+    // =========================================================================
+    // Non-panicking evaluation
+    // =========================================================================
+
+    /// Evaluate the snapshot, without panicking.
+    ///
+    /// Writes the snapshot file when updating is enabled.
+    pub fn evaluate(&self) -> AssertionResult {
+        let description = format!("trace to match snapshot '{}'", self.name);
+        let path = self.path();
+
+        let stored = match std::fs::read_to_string(&path) {
+            Ok(stored) => Some(stored.replace("\r\n", "\n")),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                let reason = format!("cannot read {}: {}", path.display(), e);
+                return AssertionResult::fail(description, reason);
+            }
+        };
+
+        let actual = match &stored {
+            Some(stored) if self.keep_stdout => {
+                let kept = stored.find(STDOUT_HEADER).map_or("", |i| &stored[i..]);
+                format!("{}{}", self.render_calls(), kept)
+            }
+            _ => self.render(),
+        };
+
+        match stored {
+            Some(stored) if stored == actual => AssertionResult::pass(description),
+            Some(stored) if !self.update => {
+                let reason = format!(
+                    "trace differs from {} (- stored, + actual):\n{}\nset {}=1 or pass \
+                     --update-snapshots to accept the new trace",
+                    path.display(),
+                    diff_lines(&stored, &actual),
+                    UPDATE_SNAPSHOTS_ENV
+                );
+                AssertionResult::fail(description, reason)
+            }
+            None if !self.update => {
+                let reason = format!(
+                    "no snapshot at {}; set {}=1 or pass --update-snapshots to record it",
+                    path.display(),
+                    UPDATE_SNAPSHOTS_ENV
+                );
+                AssertionResult::fail(description, reason)
+            }
+            _ => match self.write(&path, &actual) {
+                Ok(()) => AssertionResult::pass(description),
+                Err(e) => {
+                    let reason = format!("cannot write {}: {}", path.display(), e);
+                    AssertionResult::fail(description, reason)
+                }
+            },
+        }
+    }
+
+    /// Like [`to_match`](Self::to_match), but returns an error instead of panicking.
+    pub fn try_to_match(&self) -> Result<(), AssertionError> {
+        self.evaluate().try_to_pass()
+    }
+
+    /// The trace as it would be stored.
+    pub fn render(&self) -> String {
+        let stdout = if self.include_stdout && !self.keep_stdout {
+            Some(self.stdout.as_deref().unwrap_or(""))
+        } else {
+            None
+        };
+        let trace = render_trace(&self.tool_calls, stdout, self.working_dir.as_deref());
+        format!("# aptitude snapshot: {}\n{}", self.name, trace)
+    }
+
+    /// Location of the snapshot file.
+    pub fn path(&self) -> PathBuf {
+        let file: String = self
+            .name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{}.snap", file))
+    }
+
+    // =========================================================================
+    // Internal helpers
+    // =========================================================================
+
+    fn render_calls(&self) -> String {
+        let trace = render_trace(&self.tool_calls, None, self.working_dir.as_deref());
+        format!("# aptitude snapshot: {}\n{}", self.name, trace)
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(path, contents)
+    }
+}
+
+/// Line diff of two texts, marking removed lines `-` and added lines `+`.
+fn diff_lines(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Longest common subsequence table, filled from the end
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            out.push(format!("  {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push(format!("- {}", old[i]));
+            i += 1;
+        } else {
+            out.push(format!("+ {}", new[j]));
+            j += 1;
+        }
+    }
+    out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn call(name: &str, params: serde_json::Value) -> ToolCall {
+        ToolCall {
+            name: name.to_string(),
+            params,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_render_trace() {
+        let calls = vec![
+            call("Read", json!({"file_path": "/proj/AGENTS.md"})),
+            call(
+                "Write",
+                json!({"file_path": "/tmp/out.txt", "content": "hello"}),
+            ),
+            call("Bash", json!({"command": "cat /proj/src/lib.rs"})),
+        ];
+        let trace = render_trace(&calls, Some("done\n"), Some(Path::new("/proj")));
+        assert_eq!(
+            trace,
+            "1. Read file_path=\"AGENTS.md\"\n\
+             2. Write file_path=\"/tmp/out.txt\"\n\
+             3. Bash command=\"cat src/lib.rs\"\n\
+             \n--- stdout ---\ndone\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let diff = diff_lines("a\nb\nc\n", "a\nx\nc\n");
+        assert_eq!(diff, "  a\n- b\n+ x\n  c");
+    }
+
+    #[test]
+    fn test_snapshot_lifecycle() {
+        let tmp = tempfile::tempdir().unwrap();
+        let first = vec![call("Read", json!({"file_path": "a.md"}))];
+        let second = vec![call("Read", json!({"file_path": "b.md"}))];
+        let snapshot = |calls: &[ToolCall]| {
+            SnapshotAssertion::new(calls.to_vec(), None, "read order")
+                .snapshot_dir(tmp.path())
+                .update(false)
+        };
+
+        // A missing snapshot fails until it is recorded
+        let missing = snapshot(&first).evaluate();
+        assert!(!missing.passed);
+        assert!(missing
+            .reason
+            .unwrap()
+            .contains("--update-snapshots to record it"));
+        let path = tmp.path().join("read_order.snap");
+        assert!(!path.exists());
+        assert!(snapshot(&first).update(true).evaluate().passed);
+        assert!(path.exists());
+        assert!(snapshot(&first).evaluate().passed);
+
+        // A different trace fails with a diff
+        let result = snapshot(&second).evaluate();
+        assert!(!result.passed);
+        let reason = result.reason.unwrap();
+        assert!(reason.contains("- 1. Read file_path=\"a.md\""));
+        assert!(reason.contains("+ 1. Read file_path=\"b.md\""));
+
+        // Updating accepts the new trace
+        assert!(snapshot(&second).update(true).evaluate().passed);
+        assert!(snapshot(&second).evaluate().passed);
+    }
+
+    #[test]
+    fn test_keep_stdout() {
+        let tmp = tempfile::tempdir().unwrap();
+        let calls = vec![call("Read", json!({"file_path": "a.md"}))];
+        let snapshot = |stdout: Option<&str>| {
+            SnapshotAssertion::new(calls.clone(), stdout.map(String::from), "with stdout")
+                .snapshot_dir(tmp.path())
+                .with_stdout()
+                .update(false)
+        };
+        assert!(snapshot(Some("done")).update(true).evaluate().passed);
+
+        // Without stdout only the calls are compared, and updating keeps the stored stdout
+        assert!(!snapshot(None).evaluate().passed);
+        assert!(snapshot(None).keep_stdout().evaluate().passed);
+        assert!(snapshot(None).keep_stdout().update(true).evaluate().passed);
+        let stored = std::fs::read_to_string(tmp.path().join("with_stdout.snap")).unwrap();
+        assert!(stored.ends_with("--- stdout ---\ndone\n"));
+    }
+}
@@ -4,7 +4,8 @@
 //! - `StdoutAssertion` - Builder for assertions on stdout content
 
 use regex::Regex;
-use super::builder::AssertionResult;
+use super::builder::{truncate, AssertionResult};
+use super::error::AssertionError;
 
 /// Builder for assertions on stdout.
 ///
@@ -168,6 +169,16 @@ impl StdoutAssertion {
         self.evaluate_exists(true)
     }
 
+    /// Like [`to_exist`](Self::to_exist), but returns an error instead of panicking.
+    pub fn try_to_exist(&self) -> Result<(), AssertionError> {
+        self.evaluate_exists(true).try_to_pass()
+    }
+
+    /// Like [`to_be_empty`](Self::to_be_empty), but returns an error instead of panicking.
+    pub fn try_to_be_empty(&self) -> Result<(), AssertionError> {
+        self.evaluate_exists(false).try_to_pass()
+    }
+
     /// Evaluate that stdout is empty, without panicking.
     ///
     /// Returns an `AssertionResult` that can be inspected.
@@ -279,13 +290,7 @@ impl StdoutAssertion {
 
     fn format_stdout_preview(&self) -> String {
         match &self.stdout {
-            Some(s) if !s.is_empty() => {
-                if s.len() > 100 {
-                    format!("\"{}...\"", &s[..97])
-                } else {
-                    format!("\"{}\"", s)
-                }
-            }
+            Some(s) if !s.is_empty() => format!("\"{}\"", truncate(s, 100)),
             _ => "(empty)".to_string(),
         }
     }
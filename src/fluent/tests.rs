@@ -384,3 +384,467 @@ fn test_multiple_count_constraints() {
         .evaluate();
     assert!(!result.passed);
 }
+
+#[test]
+fn test_mcp_tool_wildcard() {
+    let calls = vec![
+        make_call("mcp__github__create_issue", json!({"title": "Bug"})),
+        make_call("mcp__github__list_issues", json!({})),
+    ];
+
+    expect_tools(&calls)
+        .tool(Tool::mcp("github", "*"))
+        .times(2)
+        .to_be_called();
+
+    expect_tools(&calls)
+        .tool(Tool::mcp("github", "create_issue"))
+        .with_params(params! {"title" => "Bug"})
+        .before(Tool::mcp("github", "list_issues"))
+        .to_be_called();
+
+    expect_tools(&calls)
+        .tool(Tool::mcp("slack", "*"))
+        .not_to_be_called();
+}
+
+#[test]
+fn test_other_tool() {
+    let calls = vec![make_call("CustomTool", json!({"arg": "x"}))];
+
+    expect_tools(&calls)
+        .tool(Tool::Other("CustomTool".to_string()))
+        .to_be_called();
+    expect_tools(&calls)
+        .tool(Tool::Other("Custom".to_string()))
+        .not_to_be_called();
+}
+
+#[test]
+fn test_category_assertion() {
+    let calls = vec![
+        make_call("Read", json!({"file_path": "/src/app.js"})),
+        make_call("MultiEdit", json!({"file_path": "/src/app.ts", "edits": []})),
+        make_call("NotebookEdit", json!({"notebook_path": "/nb/analysis.ipynb"})),
+    ];
+
+    expect_tools(&calls)
+        .category(ToolCategory::FileWrite)
+        .times(2)
+        .after(ToolCategory::FileRead)
+        .to_be_called();
+
+    // file_path also matches NotebookEdit's notebook_path
+    expect_tools(&calls)
+        .category(ToolCategory::FileWrite)
+        .with_params(params! {"file_path" => r"\.ipynb$"})
+        .to_be_called();
+
+    expect_tools(&calls)
+        .category(ToolCategory::FileWrite)
+        .with_params(params! {"file_path" => r"\.js$"})
+        .not_to_be_called();
+}
+
+#[test]
+fn test_tools_assertion() {
+    let calls = vec![
+        make_call("Grep", json!({"pattern": "TODO"})),
+        make_call("Bash", json!({"command": "ls"})),
+    ];
+
+    let result = expect_tools(&calls)
+        .tools([Tool::Write, Tool::Edit])
+        .evaluate();
+    assert!(!result.passed);
+    assert!(result.reason.unwrap().contains("'Write|Edit' was never called"));
+
+    expect_tools(&calls)
+        .tools([Tool::Grep, Tool::Glob])
+        .before(ToolCategory::Shell)
+        .to_be_called();
+}
+
+fn env_steering_calls() -> Vec<ToolCall> {
+    vec![
+        make_call("Read", json!({"file_path": "AGENTS.md"})),
+        make_call("Grep", json!({"pattern": "password"})),
+        make_call("Read", json!({"file_path": "SUMMARY.md"})),
+        make_call("Glob", json!({"pattern": "*.md"})),
+        make_call("Write", json!({"file_path": "notes.md"})),
+    ]
+}
+
+#[test]
+fn test_sequence_with_gaps() {
+    let calls = env_steering_calls();
+
+    expect_tools(&calls)
+        .sequence([
+            SequenceStep::call(Tool::Read).with_params(params! {"file_path" => "AGENTS"}),
+            SequenceStep::call(Tool::Read).with_params(params! {"file_path" => "SUMMARY"}),
+            SequenceStep::never(Tool::Read).with_params(params! {"file_path" => r"\.env$"}),
+            SequenceStep::call(Tool::Write),
+        ])
+        .to_match();
+
+    // Order matters
+    let result = expect_tools(&calls)
+        .sequence([Tool::Write, Tool::Read])
+        .evaluate();
+    assert!(!result.passed);
+    assert!(result
+        .reason
+        .unwrap()
+        .contains("step 2 (Read) was not found after call #5"));
+}
+
+#[test]
+fn test_sequence_never_step() {
+    let mut calls = env_steering_calls();
+    calls.insert(3, make_call("Read", json!({"file_path": ".env"})));
+
+    let result = expect_tools(&calls)
+        .sequence([
+            SequenceStep::call(Tool::Read).with_params(params! {"file_path" => "SUMMARY"}),
+            SequenceStep::never(Tool::Read).with_params(params! {"file_path" => r"\.env$"}),
+            SequenceStep::call(Tool::Write),
+        ])
+        .evaluate();
+    assert!(!result.passed);
+    assert!(result.reason.unwrap().contains("call #4 (Read) matched step 2"));
+
+    // The forbidden call is outside the guarded stretch
+    expect_tools(&calls)
+        .sequence([
+            SequenceStep::call(Tool::Read).with_params(params! {"file_path" => "AGENTS"}),
+            SequenceStep::never(Tool::Read).with_params(params! {"file_path" => r"\.env$"}),
+            SequenceStep::call(Tool::Read).with_params(params! {"file_path" => "SUMMARY"}),
+        ])
+        .to_match();
+}
+
+#[test]
+fn test_sequence_strict() {
+    let calls = env_steering_calls();
+
+    let result = expect_tools(&calls)
+        .sequence([Tool::Read, Tool::Read])
+        .strict()
+        .evaluate();
+    assert!(!result.passed);
+
+    expect_tools(&calls)
+        .sequence([
+            SequenceStep::call(Tool::Read),
+            SequenceStep::many(ToolSelector::AnyOf(vec![Tool::Grep, Tool::Glob])),
+            SequenceStep::call(Tool::Read),
+            SequenceStep::many(ToolSelector::AnyOf(vec![Tool::Grep, Tool::Glob])),
+            SequenceStep::call(ToolCategory::FileWrite),
+        ])
+        .strict()
+        .to_match();
+}
+
+#[test]
+#[should_panic(expected = "sequence [Edit -> Write] (strict)")]
+fn test_sequence_to_match_panics() {
+    let calls = env_steering_calls();
+    expect_tools(&calls)
+        .sequence([Tool::Edit, Tool::Write])
+        .strict()
+        .to_match();
+}
+
+#[test]
+fn test_combinators() {
+    let calls = vec![
+        make_call("Grep", json!({"pattern": "install", "path": "docs/"})),
+        make_call("Bash", json!({"command": "pnpm test"})),
+    ];
+    let expectation = expect_tools(&calls);
+
+    any_of([
+        expectation
+            .tool(Tool::Read)
+            .with_params(params! {"file_path" => "SUMMARY.md"})
+            .evaluate(),
+        expectation
+            .tool(Tool::Grep)
+            .with_params(params! {"path" => "^docs/"})
+            .evaluate(),
+    ])
+    .to_pass();
+
+    all_of([
+        expectation
+            .tool(Tool::Bash)
+            .with_params(params! {"command" => "^(npm|pnpm) test"})
+            .evaluate(),
+        not(expectation.tool(Tool::Write).evaluate()),
+    ])
+    .to_pass();
+}
+
+#[test]
+#[should_panic(expected = "none of 2 branches passed")]
+fn test_combinator_to_pass_panics() {
+    let calls = vec![make_call("Bash", json!({"command": "yarn test"}))];
+    let expectation = expect_tools(&calls);
+
+    any_of([
+        expectation
+            .tool(Tool::Bash)
+            .with_params(params! {"command" => "^npm test"})
+            .evaluate(),
+        expectation
+            .tool(Tool::Bash)
+            .with_params(params! {"command" => "^pnpm test"})
+            .evaluate(),
+    ])
+    .to_pass();
+}
+
+struct CargoRelease;
+
+impl CallMatcher for CargoRelease {
+    fn matches(&self, call: &ToolCall) -> bool {
+        let command = call.params["command"].as_str().unwrap_or_default();
+        let mut words = command.split_whitespace();
+        words.next() == Some("cargo") && words.any(|w| w == "--release")
+    }
+
+    fn describe(&self) -> String {
+        "cargo --release".to_string()
+    }
+}
+
+#[test]
+fn test_matching_closure() {
+    let calls = vec![
+        make_call("Edit", json!({"file_path": "src/lib.rs"})),
+        make_call("Bash", json!({"command": "cargo build"})),
+        make_call("Bash", json!({"command": "cargo build --release"})),
+    ];
+
+    expect_tools(&calls)
+        .tool(Tool::Bash)
+        .matching(|call: &ToolCall| call.params["command"].as_str() == Some("cargo build"))
+        .times(1)
+        .after(Tool::Edit)
+        .to_be_called();
+
+    expect_tools(&calls)
+        .tool(Tool::Bash)
+        .matching(|call: &ToolCall| call.params["command"].as_str().unwrap_or("").contains("test"))
+        .not_to_be_called();
+}
+
+#[test]
+fn test_matching_trait_impl() {
+    let calls = vec![
+        make_call("Bash", json!({"command": "cargo build --release"})),
+        make_call("Write", json!({"file_path": "dist/app"})),
+    ];
+
+    expect_tools(&calls)
+        .tool(Tool::Bash)
+        .matching(CargoRelease)
+        .before(Tool::Write)
+        .to_be_called();
+
+    let result = expect_tools(&calls[1..])
+        .tool(Tool::Bash)
+        .matching(CargoRelease)
+        .evaluate();
+    assert!(!result.passed);
+    assert_eq!(result.description, "Bash matching cargo --release called");
+
+    expect_tools(&calls)
+        .sequence([
+            SequenceStep::call(Tool::Bash).matching(CargoRelease),
+            SequenceStep::call(Tool::Write),
+        ])
+        .strict()
+        .to_match();
+}
+
+#[test]
+fn test_try_variants() {
+    let calls = vec![
+        make_call("Read", json!({"file_path": "/a.txt"})),
+        make_call("Read", json!({"file_path": "/b.txt"})),
+    ];
+    let expectation = expect_tools(&calls);
+
+    assert!(expectation.tool(Tool::Read).try_to_be_called().is_ok());
+    assert!(expectation.tool(Tool::Bash).try_not_to_be_called().is_ok());
+
+    let err = expectation.tool(Tool::Bash).try_to_be_called().unwrap_err();
+    assert_eq!(err.description, "Bash called");
+    assert!(err.reason.contains("was never called"));
+
+    let second = expectation.tool(Tool::Read).try_nth_call(2).unwrap();
+    assert!(second
+        .try_has_params(params! {"file_path" => "/b.txt"})
+        .is_ok());
+
+    let err = expectation.tool(Tool::Read).try_nth_call(3).unwrap_err();
+    assert_eq!(err.to_string(), "expected Read call #3 to exist: only 2 calls made");
+    assert!(expectation.tool(Tool::Bash).try_last_call().is_err());
+    assert!(expectation.tool(Tool::Read).try_nth_call(0).is_err());
+
+    assert!(expectation.sequence([Tool::Read, Tool::Read]).try_to_match().is_ok());
+    assert!(expectation.stdout().try_to_exist().is_err());
+    assert!(expectation.stdout().try_to_be_empty().is_ok());
+}
+
+#[test]
+fn test_soft_collects_failures() {
+    let calls = vec![make_call("Read", json!({"file_path": "/a.txt"}))];
+    let expectation = expect_tools(&calls);
+
+    let mut s = soft();
+    assert!(s.check(expectation.tool(Tool::Read).evaluate()));
+    assert!(!s.check(expectation.tool(Tool::Bash).evaluate()));
+    assert!(s.check_ok(expectation.tool(Tool::Read).try_nth_call(2)).is_none());
+    assert_eq!(s.failures().len(), 2);
+
+    let err = s.try_finish().unwrap_err();
+    assert!(err.reason.starts_with("2 of 3 soft assertions failed"));
+    assert!(err.reason.contains("\n  ✗ Bash called: "));
+    assert!(err.reason.contains("\n  ✗ Read call #2 to exist: only 1 calls made"));
+}
+
+#[test]
+#[should_panic(expected = "1 of 1 soft assertions failed")]
+fn test_soft_panics_on_drop() {
+    let calls = vec![make_call("Read", json!({"file_path": "/a.txt"}))];
+    let mut s = soft();
+    s.check(expect_tools(&calls).tool(Tool::Write).evaluate());
+}
+
+#[test]
+fn test_soft_passes() {
+    let calls = vec![make_call("Read", json!({"file_path": "/a.txt"}))];
+    let mut s = soft();
+    s.check(expect_tools(&calls).tool(Tool::Read).evaluate());
+    s.finish();
+}
+
+#[test]
+fn test_soft_expected_failure() {
+    let calls = vec![make_call("Read", json!({"file_path": "/a.txt"}))];
+    let expectation = expect_tools(&calls);
+
+    // Still failing: passes as an expected failure
+    let mut s = soft().expect_failure("#42");
+    s.check(expectation.tool(Tool::Write).evaluate());
+    s.finish();
+
+    // Fixed: reported as unexpectedly passed
+    let mut s = soft().expect_failure("#42");
+    s.check(expectation.tool(Tool::Read).evaluate());
+    let err = s.try_finish().unwrap_err();
+    assert_eq!(err.description, "soft assertions to fail (known issue #42)");
+    assert!(err.reason.starts_with("all 1 soft assertions unexpectedly passed"));
+}
+
+#[test]
+#[should_panic(expected = "unexpectedly passed")]
+fn test_soft_expected_failure_panics_on_drop() {
+    let calls = vec![make_call("Read", json!({"file_path": "/a.txt"}))];
+    let mut s = soft().expect_failure("#42");
+    s.check(expect_tools(&calls).tool(Tool::Read).evaluate());
+}
+
+#[test]
+fn test_paths_relative_to_working_dir() {
+    let calls = vec![
+        make_call("Read", json!({"file_path": "/nonexistent/alice/proj/src/config.json"})),
+        make_call("Write", json!({"file_path": "/nonexistent/alice/proj/out/report.md"})),
+    ];
+    let expectation = expect_tools(&calls).in_dir("/nonexistent/alice/proj");
+
+    expectation
+        .tool(Tool::Read)
+        .with_params(params! {"file_path" => r"^src/config\.json$"})
+        .to_be_called();
+    expectation
+        .tool(Tool::Write)
+        .last_call()
+        .has_params(params! {"file_path" => "^out/"});
+    expectation
+        .sequence([
+            SequenceStep::call(Tool::Read).with_params(params! {"file_path" => "^src/"}),
+            SequenceStep::call(Tool::Write).with_params(params! {"file_path" => "^out/"}),
+        ])
+        .to_match();
+
+    // Without a working directory only the raw path matches
+    expect_tools(&calls)
+        .tool(Tool::Read)
+        .with_params(params! {"file_path" => r"^src/config\.json$"})
+        .not_to_be_called();
+}
+
+#[test]
+fn test_failure_lists_closest_calls() {
+    let calls = vec![
+        make_call("Bash", json!({"command": "ls"})),
+        make_call("Read", json!({"file_path": "/src/main.rs", "limit": 10})),
+        make_call("Read", json!({"file_path": "/README.md", "limit": 20})),
+    ];
+
+    let result = expect_tools(&calls)
+        .tool(Tool::Read)
+        .with_params(params! {"file_path" => r"\.md$", "limit" => "< 15"})
+        .evaluate();
+    let reason = result.reason.unwrap();
+    assert!(reason.starts_with(
+        r"tool 'Read' with params {file_path='\.md$', limit='< 15'} was never called"
+    ));
+    assert!(reason.contains(
+        "closest calls:\n  call #2 (Read):\n    file_path: expected '\\.md$', got \"/src/main.rs\""
+    ));
+    assert!(reason.contains("  call #3 (Read):\n    limit: expected '< 15', got 20"));
+    assert!(!reason.contains("call #1"));
+}
+
+#[test]
+fn test_nth_call_mismatch_reason() {
+    let calls = vec![make_call("Edit", json!({"file_path": "/a.rs", "old_string": "x"}))];
+
+    let result = expect_tools(&calls)
+        .tool(Tool::Edit)
+        .nth_call(1)
+        .evaluate_params(params! {
+            "file_path" => "/a.rs",
+            "new_string" => "y",
+            "edits[0].old_string" => "z",
+        });
+    assert_eq!(
+        result.reason.as_deref(),
+        Some(
+            "2 of 3 params did not match:\n  \
+             edits[0].old_string: expected 'z', but the call has no 'edits'\n  \
+             new_string: expected 'y', but the call has no 'new_string'"
+        )
+    );
+}
+
+#[test]
+fn test_truncation_is_char_safe() {
+    let long = "日本語".repeat(80);
+    let calls = vec![make_call("Bash", json!({"command": long}))];
+
+    let trace = builder::format_tool_calls(&calls);
+    let preview: String = long.chars().take(47).collect();
+    assert!(trace.contains(&format!("Bash {{ {}... }}", preview)));
+
+    let result = expect_tools(&calls).tool(Tool::Bash).evaluate_not_called();
+    assert!(result.reason.unwrap().ends_with("..."));
+
+    let result = StdoutAssertion::new(Some(long)).evaluate_empty();
+    assert!(result.reason.unwrap().ends_with("...\""));
+}
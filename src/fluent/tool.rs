@@ -1,6 +1,11 @@
 //! Tool enum representing canonical tool names.
 //!
 //! These match the actual tool names emitted by Claude Code in JSONL logs.
+//! Tools without a dedicated variant can be named with [`Tool::Other`], and
+//! MCP server tools with [`Tool::Mcp`].
+
+/// Prefix Claude Code uses for tools provided by MCP servers.
+const MCP_PREFIX: &str = "mcp__";
 
 /// Canonical tool names as an enum for type safety.
 ///
@@ -15,7 +20,7 @@
 /// let tool = Tool::Read;
 /// assert_eq!(tool.as_str(), "Read");
 /// ```
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Tool {
     /// Read a file
     Read,
@@ -23,6 +28,8 @@ pub enum Tool {
     Write,
     /// Edit a file
     Edit,
+    /// Apply several edits to one file
+    MultiEdit,
     /// Execute a bash command
     Bash,
     /// Search for files using glob patterns
@@ -45,11 +52,47 @@ pub enum Tool {
     KillShell,
     /// Get output from a background task
     TaskOutput,
+    /// List a directory
+    ListDirectory,
+    /// Invoke a skill
+    Skill,
+    /// Run a slash command
+    SlashCommand,
+    /// Leave plan mode
+    ExitPlanMode,
+    /// Get output from a background shell
+    BashOutput,
+    /// A tool provided by an MCP server, named `mcp__<server>__<tool>`.
+    ///
+    /// The name may contain `*` wildcards (e.g. `mcp__github__*`).
+    /// Use [`Tool::mcp`] to build one from server and tool names.
+    Mcp(String),
+    /// Any other tool, matched by its exact name.
+    Other(String),
 }
 
 impl Tool {
+    /// Create an MCP tool matcher from a server and tool name.
+    ///
+    /// Either part may be `*` to match any server or tool.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use aptitude::Tool;
+    ///
+    /// let tool = Tool::mcp("github", "*");
+    /// assert!(tool.matches("mcp__github__create_issue"));
+    /// assert!(!tool.matches("mcp__slack__post_message"));
+    /// ```
+    pub fn mcp(server: &str, tool: &str) -> Self {
+        Tool::Mcp(format!("{}{}__{}", MCP_PREFIX, server, tool))
+    }
+
     /// Get the canonical string name (matches JSONL output).
     ///
+    /// For [`Tool::Mcp`] this is the name pattern, which may contain wildcards.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -58,11 +101,12 @@ impl Tool {
     /// assert_eq!(Tool::Read.as_str(), "Read");
     /// assert_eq!(Tool::Bash.as_str(), "Bash");
     /// ```
-    pub fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &str {
         match self {
             Tool::Read => "Read",
             Tool::Write => "Write",
             Tool::Edit => "Edit",
+            Tool::MultiEdit => "MultiEdit",
             Tool::Bash => "Bash",
             Tool::Glob => "Glob",
             Tool::Grep => "Grep",
@@ -74,17 +118,64 @@ impl Tool {
             Tool::TodoWrite => "TodoWrite",
             Tool::KillShell => "KillShell",
             Tool::TaskOutput => "TaskOutput",
+            Tool::ListDirectory => "LS",
+            Tool::Skill => "Skill",
+            Tool::SlashCommand => "SlashCommand",
+            Tool::ExitPlanMode => "ExitPlanMode",
+            Tool::BashOutput => "BashOutput",
+            Tool::Mcp(name) | Tool::Other(name) => name,
+        }
+    }
+
+    /// Check whether a tool call name refers to this tool.
+    ///
+    /// [`Tool::Mcp`] names support `*` wildcards; everything else must match exactly.
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Tool::Mcp(pattern) if pattern.contains('*') => glob::Pattern::new(pattern)
+                .map(|p| p.matches(name))
+                .unwrap_or(false),
+            _ => self.as_str() == name,
+        }
+    }
+
+    /// Parse a name that refers to an MCP tool (`mcp__<server>__<tool>`).
+    pub fn from_mcp_name(name: &str) -> Option<Self> {
+        let rest = name.strip_prefix(MCP_PREFIX)?;
+        let (server, tool) = rest.split_once("__")?;
+        if server.is_empty() || tool.is_empty() {
+            return None;
+        }
+        Some(Tool::Mcp(name.to_string()))
+    }
+
+    /// The MCP server part of an [`Tool::Mcp`] name.
+    pub fn mcp_server(&self) -> Option<&str> {
+        self.mcp_parts().map(|(server, _)| server)
+    }
+
+    /// The tool part of an [`Tool::Mcp`] name.
+    pub fn mcp_tool(&self) -> Option<&str> {
+        self.mcp_parts().map(|(_, tool)| tool)
+    }
+
+    fn mcp_parts(&self) -> Option<(&str, &str)> {
+        match self {
+            Tool::Mcp(name) => name.strip_prefix(MCP_PREFIX)?.split_once("__"),
+            _ => None,
         }
     }
 
     /// Get all known tool variants.
     ///
     /// Useful for generating documentation or autocomplete suggestions.
+    /// The open [`Tool::Mcp`] and [`Tool::Other`] variants are not included.
     pub fn all() -> &'static [Tool] {
         &[
             Tool::Read,
             Tool::Write,
             Tool::Edit,
+            Tool::MultiEdit,
             Tool::Bash,
             Tool::Glob,
             Tool::Grep,
@@ -96,6 +187,11 @@ impl Tool {
             Tool::TodoWrite,
             Tool::KillShell,
             Tool::TaskOutput,
+            Tool::ListDirectory,
+            Tool::Skill,
+            Tool::SlashCommand,
+            Tool::ExitPlanMode,
+            Tool::BashOutput,
         ]
     }
 }
@@ -146,4 +242,37 @@ mod tests {
         let cloned = tool.clone();
         assert_eq!(tool, cloned);
     }
+
+    #[test]
+    fn test_mcp_matching() {
+        let exact = Tool::mcp("github", "create_issue");
+        assert!(exact.matches("mcp__github__create_issue"));
+        assert!(!exact.matches("mcp__github__list_issues"));
+
+        let any_github = Tool::mcp("github", "*");
+        assert!(any_github.matches("mcp__github__list_issues"));
+        assert!(!any_github.matches("mcp__gitlab__list_issues"));
+
+        let any_server = Tool::mcp("*", "create_issue");
+        assert!(any_server.matches("mcp__gitlab__create_issue"));
+    }
+
+    #[test]
+    fn test_mcp_parts() {
+        let tool = Tool::from_mcp_name("mcp__github__create_issue").unwrap();
+        assert_eq!(tool.mcp_server(), Some("github"));
+        assert_eq!(tool.mcp_tool(), Some("create_issue"));
+        assert_eq!(Tool::Read.mcp_server(), None);
+        assert!(Tool::from_mcp_name("mcp__github").is_none());
+        assert!(Tool::from_mcp_name("Read").is_none());
+    }
+
+    #[test]
+    fn test_other_matches_exactly() {
+        let tool = Tool::Other("CustomTool".to_string());
+        assert_eq!(tool.as_str(), "CustomTool");
+        assert!(tool.matches("CustomTool"));
+        assert!(!tool.matches("customtool"));
+        assert!(Tool::ListDirectory.matches("LS"));
+    }
 }
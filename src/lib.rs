@@ -64,22 +64,32 @@ pub mod fluent;
 pub mod output;
 pub mod parser;
 pub mod prompt;
+pub mod stream;
 
 #[cfg(feature = "yaml")]
 pub mod yaml;
 
 // Core types
 pub use fluent::{
-    expect, expect_tools, params_match, ExecutionExpectation, StdoutAssertion, ToolAssertion,
-    ToolCallExpectation,
+    all_of, any_of, expect, expect_tools, not, params_match, shell, soft, AccessMode,
+    AssertionError, AssertionResult, CallMatcher, ExecutionExpectation, FileAccessAssertion,
+    SequenceAssertion, SequenceStep, ShellMatcher, SnapshotAssertion, SoftAssertions,
+    StdoutAssertion, ToolAssertion, ToolCallExpectation,
+};
+pub use parser::{
+    parse_jsonl_file as parse_session, parse_jsonl_file_with_report as parse_session_with_report,
+    parse_jsonl_file_with_subagents as parse_session_with_subagents, ParseMode, ParseReport,
+    ParseWarning, ParsedSession, SessionMetadata, SubagentTrace, ToolCall,
 };
-pub use parser::{parse_jsonl_file as parse_session, ToolCall};
 
 // Tool enum
-pub use fluent::Tool;
+pub use fluent::{Tool, ToolCategory, ToolSelector};
 
 // Agent execution
-pub use agents::{AgentHarness, AgentType, ExecutionConfig, ExecutionOutput, NormalizedResult};
+pub use agents::{
+    AgentHarness, AgentOptions, AgentType, ExecutionConfig, ExecutionOutput, NormalizedResult,
+    ToolCallObserver,
+};
 
 // Prompt builder
 pub use prompt::{prompt, PromptBuilder};
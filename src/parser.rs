@@ -1,10 +1,13 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// Maximum number of characters of an offending line kept in a [`ParseWarning`].
+const SNIPPET_LEN: usize = 80;
 
 /// A tool call extracted from Claude Code logs
 #[derive(Debug, Clone)]
@@ -14,11 +17,129 @@ pub struct ToolCall {
     pub timestamp: DateTime<Utc>,
 }
 
+/// How to handle lines that cannot be parsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Record a [`ParseWarning`] for each bad line and keep going (default).
+    ///
+    /// Session logs from agents that were killed mid-write often end with a
+    /// truncated line, so this is what the CLI uses.
+    #[default]
+    Lenient,
+    /// Fail on the first bad line.
+    Strict,
+}
+
+/// A line that could not be parsed in lenient mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    /// 1-based line number in the session log, or 0 for a subagent log that
+    /// could not be read.
+    pub line: usize,
+    /// The start of the offending line, truncated for display.
+    pub snippet: String,
+    /// The underlying parse error.
+    pub error: String,
+}
+
+impl std::fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {} ({})", self.line, self.error, self.snippet)
+    }
+}
+
+/// Summary of a session log parse.
+#[derive(Debug, Clone, Default)]
+pub struct ParseReport {
+    /// Number of lines read, including blank and skipped lines.
+    pub lines_read: usize,
+    /// Lines that were skipped because they could not be parsed.
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl ParseReport {
+    /// Check if every line parsed successfully.
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+/// Information about the session recorded alongside the messages.
+///
+/// Each field holds the first value seen in the log. Fields are `None` when
+/// the agent did not record them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SessionMetadata {
+    /// Agent session identifier.
+    pub session_id: Option<String>,
+    /// Working directory the session was recorded in.
+    pub cwd: Option<PathBuf>,
+    /// Git branch checked out in the working directory.
+    pub git_branch: Option<String>,
+    /// Version of the agent CLI.
+    pub cli_version: Option<String>,
+    /// Model that produced the assistant messages.
+    pub model: Option<String>,
+}
+
+impl SessionMetadata {
+    /// Fill in any fields that are still unset from a log entry.
+    fn absorb(&mut self, entry: &EntryTypeCheck) {
+        fill(&mut self.session_id, text(&entry.session_id));
+        fill(&mut self.cwd, text(&entry.cwd).map(PathBuf::from));
+        fill(&mut self.git_branch, text(&entry.git_branch));
+        fill(&mut self.cli_version, text(&entry.version));
+    }
+}
+
+fn fill<T>(slot: &mut Option<T>, value: Option<T>) {
+    if slot.is_none() {
+        *slot = value;
+    }
+}
+
+/// A metadata value, if it is a string. Metadata is read loosely so an odd
+/// value never costs the tool calls on its line.
+fn text(value: &Option<Value>) -> Option<String> {
+    value.as_ref().and_then(Value::as_str).map(str::to_string)
+}
+
+/// Tool calls made by a subagent, read from its own session log.
+///
+/// Claude Code writes each subagent (started with `Task`) to
+/// `<session id>/subagents/<agent id>.jsonl` next to the session log.
+#[derive(Debug, Clone, Default)]
+pub struct SubagentTrace {
+    /// Subagent identifier, from its log file name (e.g. `agent-a1b2c3`).
+    pub id: String,
+    /// Tool calls in the order the subagent made them.
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// Tool calls extracted from a session log along with the parse report.
+#[derive(Debug, Clone, Default)]
+pub struct ParsedSession {
+    /// Tool calls in the order they were made.
+    pub tool_calls: Vec<ToolCall>,
+    /// Diagnostics collected while parsing.
+    pub report: ParseReport,
+    /// Session details recorded in the log.
+    pub metadata: SessionMetadata,
+    /// Subagents started during the session, with their own tool calls.
+    pub subagents: Vec<SubagentTrace>,
+}
+
 /// Lightweight struct to check entry type before full parse
 #[derive(Debug, Deserialize)]
 struct EntryTypeCheck {
     #[serde(rename = "type")]
     entry_type: Option<String>,
+    #[serde(rename = "sessionId")]
+    session_id: Option<Value>,
+    cwd: Option<Value>,
+    #[serde(rename = "gitBranch")]
+    git_branch: Option<Value>,
+    version: Option<Value>,
 }
 
 /// Raw log entry from JSONL (only valid for assistant messages)
@@ -30,6 +151,7 @@ struct LogEntry {
 
 #[derive(Debug, Deserialize)]
 struct MessageContent {
+    model: Option<Value>,
     content: Option<Vec<ContentBlock>>,
 }
 
@@ -54,24 +176,130 @@ enum ContentBlock {
     Other,
 }
 
-/// Parse a JSONL file and extract all tool calls
+/// Parse a JSONL file and extract all tool calls.
+///
+/// Fails on the first malformed line. Use [`parse_jsonl_file_with_report`]
+/// to skip bad lines and collect warnings instead.
 pub fn parse_jsonl_file(path: &Path) -> Result<Vec<ToolCall>> {
+    Ok(parse_jsonl_file_with_report(path, ParseMode::Strict)?.tool_calls)
+}
+
+/// Parse a JSONL file, returning tool calls and a [`ParseReport`].
+///
+/// In [`ParseMode::Lenient`] malformed lines are recorded as warnings and
+/// parsing continues. In [`ParseMode::Strict`] the first malformed line is
+/// returned as an error that includes its line number.
+pub fn parse_jsonl_file_with_report(path: &Path, mode: ParseMode) -> Result<ParsedSession> {
     let file = File::open(path).context("Failed to open JSONL file")?;
-    let reader = BufReader::new(file);
-    let mut tool_calls = Vec::new();
+    parse_jsonl_reader(BufReader::new(file), mode)
+}
+
+/// Like [`parse_jsonl_file_with_report`], also parsing the subagent logs
+/// stored under the session (see [`SubagentTrace`]).
+///
+/// Problems with a subagent log never fail the session: a log that cannot be
+/// read, or a malformed line in [`ParseMode::Strict`], becomes a warning
+/// naming the subagent.
+pub fn parse_jsonl_file_with_subagents(path: &Path, mode: ParseMode) -> Result<ParsedSession> {
+    let mut session = parse_jsonl_file_with_report(path, mode)?;
+
+    for log in subagent_logs(path) {
+        let id = log
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let subagent = File::open(&log)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| parse_jsonl_reader(BufReader::new(file), ParseMode::Lenient));
+        let subagent = match subagent {
+            Ok(subagent) => subagent,
+            Err(e) => {
+                session.report.warnings.push(ParseWarning {
+                    line: 0,
+                    snippet: log.display().to_string(),
+                    error: format!("subagent {}: cannot read log: {:#}", id, e),
+                });
+                continue;
+            }
+        };
+        session
+            .report
+            .warnings
+            .extend(subagent.report.warnings.into_iter().map(|w| ParseWarning {
+                error: format!("subagent {}: {}", id, w.error),
+                ..w
+            }));
+        session.subagents.push(SubagentTrace {
+            id,
+            tool_calls: subagent.tool_calls,
+        });
+    }
+    Ok(session)
+}
+
+/// Subagent logs of a session log, sorted by name.
+fn subagent_logs(session_log: &Path) -> Vec<PathBuf> {
+    let dir = session_log.with_extension("").join("subagents");
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut logs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    logs.sort();
+    logs
+}
+
+/// Parse JSONL from any buffered reader.
+pub fn parse_jsonl_reader<R: BufRead>(reader: R, mode: ParseMode) -> Result<ParsedSession> {
+    let mut session = ParsedSession::default();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.with_context(|| format!("Failed to read line {}", line_number))?;
+        session.report.lines_read = line_number;
 
-    for line in reader.lines() {
-        let line = line.context("Failed to read line")?;
-        if let Some(calls) = parse_line_internal(&line)? {
-            tool_calls.extend(calls);
+        match parse_line_internal(&line, &mut session.metadata) {
+            Ok(Some(calls)) => session.tool_calls.extend(calls),
+            Ok(None) => {}
+            Err(e) => match mode {
+                ParseMode::Strict => {
+                    return Err(e.context(format!(
+                        "Invalid session log entry on line {}",
+                        line_number
+                    )));
+                }
+                ParseMode::Lenient => session.report.warnings.push(ParseWarning {
+                    line: line_number,
+                    snippet: snippet(&line),
+                    error: format!("{:#}", e),
+                }),
+            },
         }
     }
 
-    Ok(tool_calls)
+    Ok(session)
+}
+
+/// Truncate a line for display, respecting character boundaries.
+pub(crate) fn snippet(line: &str) -> String {
+    let line = line.trim();
+    if line.chars().count() > SNIPPET_LEN {
+        let truncated: String = line.chars().take(SNIPPET_LEN - 3).collect();
+        format!("{}...", truncated)
+    } else {
+        line.to_string()
+    }
 }
 
 /// Internal parsing: check type first, then parse full entry only for assistant messages
-fn parse_line_internal(line: &str) -> Result<Option<Vec<ToolCall>>> {
+///
+/// Session details found on the line are added to `metadata`.
+pub(crate) fn parse_line_internal(
+    line: &str,
+    metadata: &mut SessionMetadata,
+) -> Result<Option<Vec<ToolCall>>> {
     if line.trim().is_empty() {
         return Ok(None);
     }
@@ -80,6 +308,8 @@ fn parse_line_internal(line: &str) -> Result<Option<Vec<ToolCall>>> {
     let type_check: EntryTypeCheck =
         serde_json::from_str(line).context("Failed to parse JSON line")?;
 
+    metadata.absorb(&type_check);
+
     if type_check.entry_type.as_deref() != Some("assistant") {
         // Not an assistant message, skip without full parse
         return Ok(None);
@@ -89,6 +319,15 @@ fn parse_line_internal(line: &str) -> Result<Option<Vec<ToolCall>>> {
     let entry: LogEntry =
         serde_json::from_str(line).context("Failed to parse assistant message")?;
 
+    // Synthetic messages (e.g. interruptions) report a placeholder like "<synthetic>"
+    if metadata.model.is_none() {
+        metadata.model = entry
+            .message
+            .as_ref()
+            .and_then(|m| text(&m.model))
+            .filter(|m| !m.starts_with('<'));
+    }
+
     Ok(extract_tool_calls(&entry))
 }
 
@@ -129,7 +368,7 @@ mod tests {
     #[test]
     fn test_parse_tool_use() {
         let json = r#"{"type":"assistant","timestamp":"2024-01-19T12:00:00Z","message":{"content":[{"type":"tool_use","id":"123","name":"Read","input":{"file_path":"/tmp/test.txt"}}]}}"#;
-        let calls = parse_line_internal(json).unwrap().unwrap_or_default();
+        let calls = parse_line_internal(json, &mut SessionMetadata::default()).unwrap().unwrap_or_default();
         assert_eq!(calls.len(), 1);
         assert_eq!(calls[0].name, "Read");
     }
@@ -138,7 +377,7 @@ mod tests {
     fn test_skip_user_messages() {
         // User messages are skipped early (before full parse) based on type field
         let json = r#"{"type":"user","timestamp":"2024-01-19T12:00:00Z","message":{"content":"hello"}}"#;
-        let calls = parse_line_internal(json).unwrap();
+        let calls = parse_line_internal(json, &mut SessionMetadata::default()).unwrap();
         assert!(calls.is_none());
     }
 
@@ -146,7 +385,106 @@ mod tests {
     fn test_skip_system_messages() {
         // System/meta messages are also skipped
         let json = r#"{"type":"system","subtype":"turn_duration","durationMs":318950}"#;
-        let calls = parse_line_internal(json).unwrap();
+        let calls = parse_line_internal(json, &mut SessionMetadata::default()).unwrap();
         assert!(calls.is_none());
     }
+
+    const GOOD_LINE: &str = r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"1","name":"Read","input":{"file_path":"a.txt"}}]}}"#;
+
+    #[test]
+    fn test_lenient_skips_bad_lines() {
+        let input = format!("{}\n{{\"type\":\"assis\n{}\n", GOOD_LINE, GOOD_LINE);
+        let session = parse_jsonl_reader(input.as_bytes(), ParseMode::Lenient).unwrap();
+
+        assert_eq!(session.tool_calls.len(), 2);
+        assert_eq!(session.report.lines_read, 3);
+        assert_eq!(session.report.warnings.len(), 1);
+        assert_eq!(session.report.warnings[0].line, 2);
+        assert_eq!(session.report.warnings[0].snippet, r#"{"type":"assis"#);
+    }
+
+    #[test]
+    fn test_strict_reports_line_number() {
+        let input = format!("{}\n\nnot json\n", GOOD_LINE);
+        let err = parse_jsonl_reader(input.as_bytes(), ParseMode::Strict).unwrap_err();
+
+        assert!(format!("{:#}", err).contains("line 3"));
+    }
+
+    #[test]
+    fn test_extract_session_metadata() {
+        let input = concat!(
+            r#"{"type":"user","sessionId":"abc","cwd":"/proj","gitBranch":"main","version":"2.0.1","message":{"content":"hi"}}"#,
+            "\n",
+            r#"{"type":"assistant","message":{"model":"<synthetic>","content":[]}}"#,
+            "\n",
+            r#"{"type":"assistant","gitBranch":"other","message":{"model":"claude-sonnet-4-5","content":[]}}"#,
+            "\n",
+        );
+        let session = parse_jsonl_reader(input.as_bytes(), ParseMode::Strict).unwrap();
+
+        assert_eq!(
+            session.metadata,
+            SessionMetadata {
+                session_id: Some("abc".to_string()),
+                cwd: Some(PathBuf::from("/proj")),
+                git_branch: Some("main".to_string()),
+                cli_version: Some("2.0.1".to_string()),
+                model: Some("claude-sonnet-4-5".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_odd_metadata_keeps_tool_calls() {
+        let input = concat!(
+            r#"{"type":"assistant","cwd":42,"version":2.1,"sessionId":null,"message":{"model":7,"content":[{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"a.txt"}}]}}"#,
+            "\n",
+            r#"{"type":"user","cwd":"/proj","version":"2.0.1","message":{"content":"hi"}}"#,
+            "\n",
+        );
+        let session = parse_jsonl_reader(input.as_bytes(), ParseMode::Strict).unwrap();
+
+        assert_eq!(session.tool_calls.len(), 1);
+        assert_eq!(session.metadata.cwd, Some(PathBuf::from("/proj")));
+        assert_eq!(session.metadata.cli_version.as_deref(), Some("2.0.1"));
+        assert_eq!(session.metadata.model, None);
+    }
+
+    #[test]
+    fn test_parse_subagent_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("abc.jsonl");
+        std::fs::write(&log, format!("{}\n", GOOD_LINE)).unwrap();
+        let subagents = dir.path().join("abc").join("subagents");
+        std::fs::create_dir_all(&subagents).unwrap();
+        let env_read = GOOD_LINE.replace("a.txt", ".env");
+        std::fs::write(subagents.join("agent-b2.jsonl"), format!("{}\n", env_read)).unwrap();
+        std::fs::write(subagents.join("agent-a1.jsonl"), "not json\n").unwrap();
+
+        let session = parse_jsonl_file_with_subagents(&log, ParseMode::Strict).unwrap();
+        assert_eq!(session.tool_calls.len(), 1);
+        let ids: Vec<&str> = session.subagents.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["agent-a1", "agent-b2"]);
+        assert_eq!(session.subagents[1].tool_calls[0].params["file_path"], ".env");
+        assert!(session.report.warnings[0]
+            .error
+            .starts_with("subagent agent-a1: "));
+
+
+        // The plain parsers only read the session's own log
+        assert!(parse_jsonl_file_with_report(&log, ParseMode::Strict)
+            .unwrap()
+            .subagents
+            .is_empty());
+        assert_eq!(parse_jsonl_file(&log).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_snippet_truncates_on_char_boundary() {
+        let line = "é".repeat(100);
+        let s = snippet(&line);
+        assert_eq!(s.chars().count(), SNIPPET_LEN);
+        assert!(s.ends_with("..."));
+    }
 }
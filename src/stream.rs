@@ -0,0 +1,211 @@
+//! Incremental session log parsing for live tool-call display.
+//!
+//! Session logs are written one JSON line at a time while the agent runs.
+//! [`IncrementalParser`] turns arbitrary byte chunks into tool calls as soon
+//! as each line is complete, and [`SessionTail`] follows a growing log file
+//! on disk.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use aptitude::stream::SessionTail;
+//!
+//! let mut tail = SessionTail::new("session.jsonl");
+//! loop {
+//!     for call in tail.poll()? {
+//!         println!("{}", call.name);
+//!     }
+//!     std::thread::sleep(std::time::Duration::from_millis(100));
+//! }
+//! ```
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::parser::{
+    parse_line_internal, snippet, ParseReport, ParseWarning, SessionMetadata, ToolCall,
+};
+
+/// Parser that accepts a session log in chunks.
+///
+/// Bytes after the last newline are buffered until the rest of the line
+/// arrives, so a line split across reads is never reported as malformed.
+/// Malformed complete lines are recorded in the [`ParseReport`] and skipped.
+#[derive(Debug, Default)]
+pub struct IncrementalParser {
+    pending: Vec<u8>,
+    report: ParseReport,
+    metadata: SessionMetadata,
+}
+
+impl IncrementalParser {
+    /// Create a parser with no buffered input.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk of bytes and return tool calls from any lines it completes.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<ToolCall> {
+        self.pending.extend_from_slice(bytes);
+
+        let mut calls = Vec::new();
+        while let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            calls.extend(self.parse_line(&line[..line.len() - 1]));
+        }
+        calls
+    }
+
+    /// Parse any buffered partial line, treating end of input as end of line.
+    pub fn finish(&mut self) -> Vec<ToolCall> {
+        if self.pending.is_empty() {
+            return Vec::new();
+        }
+        let line = std::mem::take(&mut self.pending);
+        self.parse_line(&line)
+    }
+
+    /// Diagnostics collected so far.
+    pub fn report(&self) -> &ParseReport {
+        &self.report
+    }
+
+    /// Session details seen so far.
+    pub fn metadata(&self) -> &SessionMetadata {
+        &self.metadata
+    }
+
+    fn parse_line(&mut self, bytes: &[u8]) -> Vec<ToolCall> {
+        self.report.lines_read += 1;
+        let line = String::from_utf8_lossy(bytes);
+
+        match parse_line_internal(&line, &mut self.metadata) {
+            Ok(calls) => calls.unwrap_or_default(),
+            Err(e) => {
+                self.report.warnings.push(ParseWarning {
+                    line: self.report.lines_read,
+                    snippet: snippet(&line),
+                    error: format!("{:#}", e),
+                });
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// Follows a session log file that is still being written.
+///
+/// Each call to [`poll`](Self::poll) reads whatever was appended since the
+/// previous call. A file that does not exist yet is treated as empty.
+#[derive(Debug)]
+pub struct SessionTail {
+    path: PathBuf,
+    offset: u64,
+    parser: IncrementalParser,
+}
+
+impl SessionTail {
+    /// Start tailing the given file from the beginning.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            offset: 0,
+            parser: IncrementalParser::new(),
+        }
+    }
+
+    /// Path of the file being tailed.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read newly appended data and return the tool calls it completes.
+    pub fn poll(&mut self) -> Result<Vec<ToolCall>> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context("Failed to open session log"),
+        };
+
+        file.seek(SeekFrom::Start(self.offset))
+            .context("Failed to seek session log")?;
+        let mut buf = Vec::new();
+        let read = file
+            .read_to_end(&mut buf)
+            .context("Failed to read session log")?;
+        self.offset += read as u64;
+
+        Ok(self.parser.feed(&buf))
+    }
+
+    /// Read any remaining data, including a final line without a newline.
+    pub fn finish(&mut self) -> Result<Vec<ToolCall>> {
+        let mut calls = self.poll()?;
+        calls.extend(self.parser.finish());
+        Ok(calls)
+    }
+
+    /// Diagnostics collected so far.
+    pub fn report(&self) -> &ParseReport {
+        self.parser.report()
+    }
+
+    /// Session details seen so far.
+    pub fn metadata(&self) -> &SessionMetadata {
+        self.parser.metadata()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const READ_LINE: &str = r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"1","name":"Read","input":{"file_path":"a.txt"}}]}}"#;
+
+    #[test]
+    fn test_feed_split_line() {
+        let mut parser = IncrementalParser::new();
+        let (head, tail) = READ_LINE.split_at(40);
+
+        assert!(parser.feed(head.as_bytes()).is_empty());
+        assert!(parser.feed(tail.as_bytes()).is_empty());
+        let calls = parser.feed(b"\n");
+
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name, "Read");
+        assert!(parser.report().is_clean());
+    }
+
+    #[test]
+    fn test_finish_reports_truncated_line() {
+        let mut parser = IncrementalParser::new();
+        parser.feed(format!("{}\n{{\"type\":", READ_LINE).as_bytes());
+
+        assert!(parser.finish().is_empty());
+        assert_eq!(parser.report().lines_read, 2);
+        assert_eq!(parser.report().warnings.len(), 1);
+        assert_eq!(parser.report().warnings[0].line, 2);
+    }
+
+    #[test]
+    fn test_session_tail_reads_appended_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.jsonl");
+        let mut tail = SessionTail::new(&path);
+
+        // Missing file is not an error
+        assert!(tail.poll().unwrap().is_empty());
+
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "{}", READ_LINE).unwrap();
+        assert_eq!(tail.poll().unwrap().len(), 1);
+        assert!(tail.poll().unwrap().is_empty());
+
+        write!(file, "{}", READ_LINE).unwrap();
+        assert!(tail.poll().unwrap().is_empty());
+        assert_eq!(tail.finish().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,153 @@
+//! Selecting which tests of a suite to run.
+//!
+//! Tests are selected after discovery by their `skip` and `only` fields and
+//! by the `--tag`, `--exclude-tag` and `--filter` options. A test that is not
+//! selected is reported as skipped with the reason, not dropped.
+
+use regex::Regex;
+
+use super::parser::Test;
+
+/// Tag and name filters for a test run.
+///
+/// # Example
+///
+/// ```rust
+/// use aptitude::yaml::{parse_test, TestFilter};
+///
+/// let test = parse_test("name: Smoke read\nprompt: P\ntags: [smoke]\n").unwrap();
+/// let filter = TestFilter::new().tag("smoke").name_matching("read").unwrap();
+/// assert_eq!(filter.skip_reason(&test, false), None);
+///
+/// let filter = TestFilter::new().exclude_tag("smoke");
+/// assert_eq!(filter.skip_reason(&test, false).unwrap(), "tagged 'smoke'");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TestFilter {
+    tags: Vec<String>,
+    exclude_tags: Vec<String>,
+    name: Option<Regex>,
+}
+
+impl TestFilter {
+    /// A filter that selects every test not marked `skip`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Select tests with this tag. With several tags, a test needs any one.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Skip tests with this tag.
+    pub fn exclude_tag(mut self, tag: impl Into<String>) -> Self {
+        self.exclude_tags.push(tag.into());
+        self
+    }
+
+    /// Select tests whose name matches this regex.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is not a valid regex.
+    pub fn name_matching(mut self, pattern: &str) -> Result<Self, regex::Error> {
+        self.name = Some(Regex::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Why a test is not run, or `None` if it is selected.
+    ///
+    /// `only_active` is whether any test in the run is marked `only: true`;
+    /// other tests are then skipped.
+    pub fn skip_reason(&self, test: &Test, only_active: bool) -> Option<String> {
+        if let Some(skip) = test.skip.as_ref().filter(|s| s.enabled()) {
+            return Some(match skip.reason() {
+                Some(reason) => format!("skip: {}", reason),
+                None => "skip: true".to_string(),
+            });
+        }
+        if only_active && !test.only {
+            return Some("another test is marked 'only'".to_string());
+        }
+        if let Some(tag) = test.tags.iter().find(|t| self.exclude_tags.contains(t)) {
+            return Some(format!("tagged '{}'", tag));
+        }
+        if !self.tags.is_empty() && !test.tags.iter().any(|t| self.tags.contains(t)) {
+            return Some(format!("not tagged {}", quoted_list(&self.tags)));
+        }
+        if let Some(name) = self.name.as_ref().filter(|re| !re.is_match(&test.name)) {
+            return Some(format!("name does not match '{}'", name));
+        }
+        None
+    }
+}
+
+/// `'a'`, or `'a' or 'b'` for several.
+fn quoted_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|i| format!("'{}'", i))
+        .collect::<Vec<_>>()
+        .join(" or ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaml::parse_test;
+
+    fn test(extra: &str) -> Test {
+        parse_test(&format!("name: Read config\nprompt: P\n{}", extra)).unwrap()
+    }
+
+    #[test]
+    fn test_skip_and_only() {
+        let filter = TestFilter::new();
+        assert_eq!(filter.skip_reason(&test(""), false), None);
+        assert_eq!(
+            filter
+                .skip_reason(&test("skip: \"flaky on CI\"\n"), false)
+                .unwrap(),
+            "skip: flaky on CI"
+        );
+        assert_eq!(
+            filter.skip_reason(&test("skip: true\n"), false).unwrap(),
+            "skip: true"
+        );
+        assert_eq!(filter.skip_reason(&test("skip: false\n"), false), None);
+
+        assert_eq!(filter.skip_reason(&test("only: true\n"), true), None);
+        assert!(filter.skip_reason(&test(""), true).is_some());
+        // skip wins over only
+        let reason = filter.skip_reason(&test("only: true\nskip: wip\n"), true);
+        assert_eq!(reason.unwrap(), "skip: wip");
+    }
+
+    #[test]
+    fn test_tags_and_name() {
+        let smoke = test("tags: [smoke, fast]\n");
+        let slow = test("tags: [slow]\n");
+
+        let filter = TestFilter::new().tag("smoke").tag("nightly");
+        assert_eq!(filter.skip_reason(&smoke, false), None);
+        assert_eq!(
+            filter.skip_reason(&slow, false).unwrap(),
+            "not tagged 'smoke' or 'nightly'"
+        );
+
+        let filter = TestFilter::new().exclude_tag("slow");
+        assert_eq!(filter.skip_reason(&smoke, false), None);
+        assert_eq!(filter.skip_reason(&slow, false).unwrap(), "tagged 'slow'");
+
+        let filter = TestFilter::new().name_matching("^Read").unwrap();
+        assert_eq!(filter.skip_reason(&smoke, false), None);
+        let filter = TestFilter::new().name_matching("write").unwrap();
+        assert_eq!(
+            filter.skip_reason(&smoke, false).unwrap(),
+            "name does not match 'write'"
+        );
+        assert!(TestFilter::new().name_matching("(").is_err());
+    }
+}
@@ -0,0 +1,859 @@
+//! Static checks over test suites.
+//!
+//! `aptitude lint` loads every discovered test file without running an agent
+//! and reports problems that loading alone accepts: patterns written in the
+//! wrong syntax, assertions that contradict themselves or can never fail,
+//! duplicate test names, tests without assertions and legacy tool aliases.
+
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::fluent::{Tool, ToolCategory};
+
+use super::parser::{parse_tool_name, Assertion, SequenceEntry, StdoutConstraints, Test};
+use super::validate::{
+    assertion_spans, check_kind, child_spans, from_yaml_error, locate, regex_error, validate_test,
+    Diagnostic, Span,
+};
+
+/// A rule checked by [`lint_files`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Lint {
+    /// The file does not load (YAML, unknown field or validation error).
+    Invalid,
+    /// A parameter value looks like a glob but is matched as a regex, a glob
+    /// field looks like a regex, or a pattern does not compile.
+    AmbiguousPattern,
+    /// The assertion mixes kinds, or sets fields its kind ignores.
+    MixedKinds,
+    /// The assertion can never pass.
+    Contradiction,
+    /// The assertion can never fail.
+    NeverFails,
+    /// Another test file uses the same name.
+    DuplicateName,
+    /// The test checks nothing.
+    NoAssertions,
+    /// A tool name only resolves through a legacy snake_case alias.
+    LegacyAlias,
+}
+
+impl Lint {
+    /// The rule name used in reports, e.g. `legacy-alias`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Lint::Invalid => "invalid",
+            Lint::AmbiguousPattern => "ambiguous-pattern",
+            Lint::MixedKinds => "mixed-kinds",
+            Lint::Contradiction => "contradiction",
+            Lint::NeverFails => "never-fails",
+            Lint::DuplicateName => "duplicate-name",
+            Lint::NoAssertions => "no-assertions",
+            Lint::LegacyAlias => "legacy-alias",
+        }
+    }
+
+    /// Errors make a test fail or misbehave; warnings make it weaker than
+    /// it looks.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Lint::Invalid | Lint::MixedKinds | Lint::Contradiction => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+/// How serious a [`Lint`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The test cannot do what it says.
+    Error,
+    /// The test runs, but probably not as intended.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in a test file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    /// Test file the problem is in.
+    pub path: PathBuf,
+    /// Rule that found it.
+    pub lint: Lint,
+    /// Severity of the rule.
+    pub severity: Severity,
+    /// Location, message and suggestion.
+    #[serde(flatten)]
+    pub diagnostic: Diagnostic,
+}
+
+impl Finding {
+    fn new(path: &Path, lint: Lint, diagnostic: Diagnostic) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            lint,
+            severity: lint.severity(),
+            diagnostic,
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    /// `path:line:column: warning[legacy-alias]: message`, as compilers do.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let d = &self.diagnostic;
+        write!(f, "{}:", self.path.display())?;
+        if let (Some(line), Some(column)) = (d.line, d.column) {
+            write!(f, "{}:{}:", line, column)?;
+        }
+        write!(f, " {}[{}]: {}", self.severity, self.lint.code(), d.message)?;
+        if let Some(suggestion) = &d.suggestion {
+            write!(f, "; did you mean '{}'?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lint test files, in order, and check names across them.
+///
+/// Unreadable files are reported as [`Lint::Invalid`].
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let files = discover_tests(Path::new("tests"), &Config::default())?;
+/// for finding in lint_files(&files) {
+///     println!("{}", finding);
+/// }
+/// ```
+pub fn lint_files(paths: &[PathBuf]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut names: HashMap<String, &Path> = HashMap::new();
+
+    for path in paths {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                let message = format!("cannot read file: {}", e);
+                findings.push(Finding::new(path, Lint::Invalid, Diagnostic::new(message)));
+                continue;
+            }
+        };
+        let (test, lints) = lint_source(&source);
+        findings.extend(
+            lints
+                .into_iter()
+                .map(|(lint, d)| Finding::new(path, lint, d)),
+        );
+
+        let Some(test) = test else { continue };
+        match names.get(&test.name) {
+            Some(first) => {
+                let message = format!(
+                    "test name '{}' is also used by {}",
+                    test.name,
+                    first.display()
+                );
+                let diagnostic = Diagnostic::new(message).at(locate(&source, "name", &test.name));
+                findings.push(Finding::new(path, Lint::DuplicateName, diagnostic));
+            }
+            None => {
+                names.insert(test.name.clone(), path);
+            }
+        }
+    }
+    findings
+}
+
+/// Lint one test file's contents, returning the test if it parses.
+fn lint_source(source: &str) -> (Option<Test>, Vec<(Lint, Diagnostic)>) {
+    let test: Test = match serde_yaml::from_str(source) {
+        Ok(test) => test,
+        Err(e) => return (None, vec![(Lint::Invalid, from_yaml_error(&e))]),
+    };
+
+    let mut out: Vec<(Lint, Diagnostic)> = validate_test(&test, source)
+        .into_iter()
+        .map(|d| (Lint::Invalid, d))
+        .collect();
+
+    let snapshot = test.snapshot.is_some_and(|s| s.enabled());
+    if test.assertions.is_empty() && !snapshot {
+        let message = format!("test '{}' has no assertions and no snapshot", test.name);
+        let diagnostic = Diagnostic::new(message).at(locate(source, "name", &test.name));
+        out.push((Lint::NoAssertions, diagnostic));
+    }
+
+    // Lint every matrix case, reporting each problem once
+    let spans = assertion_spans(source, test.assertions.len());
+    let mut linter = Linter {
+        source,
+        span: Span::document(source),
+        out: Vec::new(),
+    };
+    for case in test.cases() {
+        for (assertion, span) in case.assertions.iter().zip(&spans) {
+            linter.span = *span;
+            linter.assertion_tree(assertion);
+            if let Some(reason) = outcome(assertion).and_then(Outcome::always_passes) {
+                let message = format!("assertion can never fail: {}", reason);
+                linter.push(Lint::NeverFails, message, linter.location(assertion));
+            }
+        }
+    }
+    for finding in linter.out {
+        // Validation also rejects these; report them under their own rule
+        if finding.0 == Lint::MixedKinds {
+            out.retain(|(lint, d)| !(*lint == Lint::Invalid && *d == finding.1));
+        }
+        if !out.contains(&finding) {
+            out.push(finding);
+        }
+    }
+    (Some(test), out)
+}
+
+struct Linter<'a> {
+    source: &'a str,
+    /// Span of the assertion being linted.
+    span: Span,
+    out: Vec<(Lint, Diagnostic)>,
+}
+
+impl Linter<'_> {
+    fn push(&mut self, lint: Lint, message: String, location: Option<(usize, usize)>) {
+        self.out.push((lint, Diagnostic::new(message).at(location)));
+    }
+
+    /// Where an assertion starts, if it can be found.
+    fn location(&self, assertion: &Assertion) -> Option<(usize, usize)> {
+        if let Some(tool) = &assertion.tool {
+            return self.locate("tool", tool);
+        }
+        let stdout = assertion.stdout.as_ref();
+        let patterns = [
+            ("contains", stdout.and_then(|s| s.contains.as_deref())),
+            ("matches", stdout.and_then(|s| s.matches.as_deref())),
+            (
+                "path",
+                assertion.file_access.as_ref().map(|c| c.path.as_str()),
+            ),
+        ];
+        patterns
+            .into_iter()
+            .find_map(|(key, value)| self.locate(key, value?))
+    }
+
+    /// Find `key: value` within the current assertion.
+    fn locate(&self, key: &str, value: &str) -> Option<(usize, usize)> {
+        self.span.locate(self.source, key, value)
+    }
+
+    fn assertion_tree(&mut self, assertion: &Assertion) {
+        for diagnostic in check_kind(assertion, self.source, self.span) {
+            self.out.push((Lint::MixedKinds, diagnostic));
+        }
+        for (key, selector) in [
+            ("tool", &assertion.tool),
+            ("called_after", &assertion.called_after),
+            ("called_before", &assertion.called_before),
+        ] {
+            if let Some(selector) = selector {
+                self.legacy_aliases(key, selector);
+            }
+        }
+
+        let param_maps = assertion
+            .params
+            .iter()
+            .chain(&assertion.first_call_params)
+            .chain(&assertion.last_call_params)
+            .chain(assertion.nth_call_params.iter().flat_map(|m| m.values()));
+        for params in param_maps {
+            for (key, pattern) in params {
+                self.param_pattern(key, pattern);
+            }
+        }
+        for (key, pattern) in [
+            ("reads_file", &assertion.reads_file),
+            ("writes_file", &assertion.writes_file),
+        ] {
+            if let Some(pattern) = pattern {
+                self.glob_pattern(key, pattern);
+            }
+        }
+        if let Some(constraints) = &assertion.file_access {
+            self.glob_pattern("path", &constraints.path);
+        }
+        if let Some(stdout) = &assertion.stdout {
+            self.stdout_patterns(stdout);
+        }
+        for entry in assertion.sequence.iter().flatten() {
+            self.sequence_entry(entry);
+        }
+
+        if let Some(message) = contradiction(assertion) {
+            self.push(Lint::Contradiction, message, self.location(assertion));
+        }
+
+        let children = assertion
+            .any_of
+            .iter()
+            .chain(&assertion.all_of)
+            .flatten()
+            .chain(assertion.not.as_deref());
+        let parent = self.span;
+        for (child, span) in children.zip(child_spans(assertion, self.source, parent)) {
+            self.span = span;
+            self.assertion_tree(child);
+        }
+        self.span = parent;
+    }
+
+    fn sequence_entry(&mut self, entry: &SequenceEntry) {
+        self.legacy_aliases("tool", &entry.tool);
+        for (key, pattern) in entry.params.iter().flatten() {
+            self.param_pattern(key, pattern);
+        }
+    }
+
+    /// Report names in a selector that are legacy aliases of a tool.
+    fn legacy_aliases(&mut self, key: &str, selector: &str) {
+        if ToolCategory::from_name(selector).is_some() {
+            return;
+        }
+        for name in selector.split('|').map(str::trim) {
+            let Ok(tool) = parse_tool_name(name) else {
+                continue;
+            };
+            if matches!(tool, Tool::Mcp(_) | Tool::Other(_))
+                || name.eq_ignore_ascii_case(tool.as_str())
+            {
+                continue;
+            }
+            let message = format!("'{}' in '{}' is a legacy alias", name, key);
+            let diagnostic = Diagnostic::new(message)
+                .at(self.locate(key, selector))
+                .suggest(Some(tool.as_str()));
+            self.out.push((Lint::LegacyAlias, diagnostic));
+        }
+    }
+
+    /// Parameter values are regexes, compared exactly when they don't compile.
+    fn param_pattern(&mut self, key: &str, pattern: &str) {
+        let location = self.locate(key, pattern);
+        let compiled = Regex::new(pattern);
+        let message = match (&compiled, looks_like_glob(pattern)) {
+            (Err(_), true) => format!(
+                "'{}' in '{}' looks like a glob, but params are regexes; it is not a valid \
+                 regex and is compared literally",
+                pattern, key
+            ),
+            (Ok(_), true) => format!(
+                "'{}' in '{}' looks like a glob, but params are regexes, where '*' repeats \
+                 the previous character",
+                pattern, key
+            ),
+            (Err(e), false) => format!(
+                "'{}' in '{}' is not a valid regex ({}) and is compared literally",
+                pattern,
+                key,
+                regex_error(e)
+            ),
+            (Ok(_), false) => return,
+        };
+        let suggestion = if looks_like_glob(pattern) {
+            glob_to_regex(pattern)
+        } else {
+            regex::escape(pattern)
+        };
+        let diagnostic = Diagnostic::new(message)
+            .at(location)
+            .suggest(Some(&suggestion));
+        self.out.push((Lint::AmbiguousPattern, diagnostic));
+    }
+
+    /// Invalid globs are reported by validation; valid ones may still be
+    /// regexes written by mistake.
+    fn glob_pattern(&mut self, key: &str, pattern: &str) {
+        if glob::Pattern::new(pattern).is_err() || !looks_like_regex(pattern) {
+            return;
+        }
+        let message = format!(
+            "'{}' in '{}' looks like a regex, but '{}' is a glob",
+            pattern, key, key
+        );
+        self.push(Lint::AmbiguousPattern, message, self.locate(key, pattern));
+    }
+
+    fn stdout_patterns(&mut self, stdout: &StdoutConstraints) {
+        for (key, text) in [
+            ("contains", &stdout.contains),
+            ("not_contains", &stdout.not_contains),
+        ] {
+            if let Some(text) = text.as_deref().filter(|t| looks_like_regex(t)) {
+                let message = format!(
+                    "'{}' in '{}' looks like a regex, but '{}' compares plain text; use \
+                     'matches' for a regex",
+                    text, key, key
+                );
+                self.push(Lint::AmbiguousPattern, message, self.locate(key, text));
+            }
+        }
+    }
+}
+
+/// Whether a leaf assertion can never pass, and why.
+fn contradiction(assertion: &Assertion) -> Option<String> {
+    if assertion.tool.is_some() && assertion.sequence.is_none() {
+        if !assertion.called {
+            for (key, value) in [
+                ("called_after", assertion.called_after.is_some()),
+                ("called_before", assertion.called_before.is_some()),
+                ("first_call_params", assertion.first_call_params.is_some()),
+                ("last_call_params", assertion.last_call_params.is_some()),
+                ("nth_call_params", assertion.nth_call_params.is_some()),
+            ] {
+                if value {
+                    return Some(format!(
+                        "'called: false' contradicts '{}', which needs a call",
+                        key
+                    ));
+                }
+            }
+        } else {
+            if assertion.call_count == Some(0) {
+                return Some(
+                    "'call_count: 0' contradicts 'called: true'; use 'called: false'".to_string(),
+                );
+            }
+            if assertion.max_calls == Some(0) {
+                return Some(
+                    "'max_calls: 0' contradicts 'called: true'; use 'called: false'".to_string(),
+                );
+            }
+            if let (Some(min), Some(max)) = (assertion.min_calls, assertion.max_calls) {
+                if min > max {
+                    return Some(format!(
+                        "'min_calls: {}' is greater than 'max_calls: {}'",
+                        min, max
+                    ));
+                }
+            }
+            if let Some(count) = assertion.call_count {
+                let below = assertion.min_calls.is_some_and(|min| count < min);
+                let above = assertion.max_calls.is_some_and(|max| count > max);
+                if below || above {
+                    return Some(format!(
+                        "'call_count: {}' is outside 'min_calls'..'max_calls'",
+                        count
+                    ));
+                }
+            }
+        }
+    }
+
+    if let Some(stdout) = &assertion.stdout {
+        if stdout.not_contains.as_deref() == Some("") {
+            return Some("every output contains '', so 'not_contains' always fails".to_string());
+        }
+        if let Some(pattern) = &stdout.not_matches {
+            if matches_empty(pattern) {
+                return Some(format!(
+                    "'not_matches: {}' matches every output, so it always fails",
+                    pattern
+                ));
+            }
+        }
+        let content = stdout.contains.as_deref().is_some_and(|s| !s.is_empty())
+            || stdout.matches.as_deref().is_some_and(|p| !matches_empty(p));
+        if !stdout.exists && content {
+            return Some(
+                "'exists: false' requires empty output, which cannot contain text".to_string(),
+            );
+        }
+    }
+
+    if assertion.any_of.as_ref().is_some_and(Vec::is_empty) {
+        return Some("'any_of' has no assertions, so it always fails".to_string());
+    }
+    if let Some(reason) = assertion
+        .not
+        .as_deref()
+        .and_then(outcome)
+        .and_then(Outcome::always_passes)
+    {
+        return Some(format!(
+            "'not' negates an assertion that always passes: {}",
+            reason
+        ));
+    }
+    None
+}
+
+/// An assertion whose result does not depend on the trace.
+enum Outcome {
+    AlwaysPasses(String),
+    NeverPasses(String),
+}
+
+impl Outcome {
+    fn always_passes(self) -> Option<String> {
+        match self {
+            Outcome::AlwaysPasses(reason) => Some(reason),
+            Outcome::NeverPasses(_) => None,
+        }
+    }
+}
+
+/// Whether an assertion always passes or never passes.
+fn outcome(assertion: &Assertion) -> Option<Outcome> {
+    if let Some(reason) = contradiction(assertion) {
+        return Some(Outcome::NeverPasses(reason));
+    }
+    if let Some(children) = &assertion.all_of {
+        if children.is_empty() {
+            return Some(Outcome::AlwaysPasses("'all_of' is empty".to_string()));
+        }
+        let outcomes: Vec<_> = children.iter().map(outcome).collect();
+        let mut reasons = Vec::new();
+        for child in outcomes {
+            match child {
+                Some(Outcome::NeverPasses(reason)) => return Some(Outcome::NeverPasses(reason)),
+                Some(Outcome::AlwaysPasses(reason)) => reasons.push(reason),
+                None => {}
+            }
+        }
+        return (reasons.len() == children.len())
+            .then(|| Outcome::AlwaysPasses(reasons.join("; ")));
+    }
+    if let Some(children) = &assertion.any_of {
+        return children
+            .iter()
+            .filter_map(outcome)
+            .find_map(|o| o.always_passes())
+            .map(Outcome::AlwaysPasses);
+    }
+    if let Some(child) = &assertion.not {
+        return match outcome(child)? {
+            Outcome::NeverPasses(reason) => {
+                Some(Outcome::AlwaysPasses(format!("'not' negates: {}", reason)))
+            }
+            Outcome::AlwaysPasses(reason) => Some(Outcome::NeverPasses(reason)),
+        };
+    }
+    if let Some(entries) = &assertion.sequence {
+        if entries.iter().all(|e| e.many) {
+            return Some(Outcome::AlwaysPasses(
+                "every sequence step has 'many: true', so an empty trace matches".to_string(),
+            ));
+        }
+    }
+    if let Some(stdout) = &assertion.stdout {
+        let universal = [&stdout.contains, &stdout.matches].into_iter().all(|p| {
+            p.as_deref()
+                .is_none_or(|p| p.is_empty() || matches_empty(p))
+        });
+        let used = stdout.contains.is_some() || stdout.matches.is_some();
+        let negated = stdout.not_contains.is_some() || stdout.not_matches.is_some();
+        if stdout.exists && used && universal && !negated {
+            return Some(Outcome::AlwaysPasses(
+                "the stdout pattern matches any output, so only existence is checked".to_string(),
+            ));
+        }
+    }
+    None
+}
+
+fn matches_empty(pattern: &str) -> bool {
+    Regex::new(pattern).is_ok_and(|re| re.is_match(""))
+}
+
+/// A `*` that does not follow something a regex can repeat, as in `*.rs`,
+/// `src/*` or `**/test`.
+fn looks_like_glob(pattern: &str) -> bool {
+    let chars: Vec<char> = pattern.chars().collect();
+    chars.iter().enumerate().any(|(i, &c)| {
+        if c != '*' {
+            return false;
+        }
+        match i.checked_sub(1).map(|p| (chars[p], p)) {
+            None => true,
+            Some((prev, p)) => {
+                let escaped = p > 0 && chars[p - 1] == '\\';
+                !escaped && !matches!(prev, '.' | ')' | ']' | '}' | '\\')
+            }
+        }
+    })
+}
+
+/// Regex syntax in a glob: `.*`, `.+`, `\.`, or a `^`/`$` anchor.
+fn looks_like_regex(pattern: &str) -> bool {
+    pattern.starts_with('^')
+        || (pattern.ends_with('$') && !pattern.ends_with("\\$"))
+        || pattern.contains(".+")
+        || pattern.contains("\\.")
+        || pattern
+            .match_indices(".*")
+            .any(|(i, _)| i > 0 && !pattern[..i].ends_with('/'))
+}
+
+/// The regex equivalent of a glob, as a suggestion. Params are unanchored,
+/// so leading wildcards are dropped and the end is anchored unless it is a
+/// wildcard too.
+fn glob_to_regex(glob: &str) -> String {
+    let mut out = String::new();
+    let mut rest = glob;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("**/") {
+            out.push_str("(.*/)?");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("**") {
+            out.push_str(".*");
+            rest = after;
+        } else {
+            match c {
+                '*' => out.push_str("[^/]*"),
+                '?' => out.push_str("[^/]"),
+                _ => out.push_str(&regex::escape(&c.to_string())),
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    let wildcards = ["(.*/)?", ".*", "[^/]*"];
+    let mut regex = out.as_str();
+    while let Some(stripped) = wildcards.iter().find_map(|w| regex.strip_prefix(w)) {
+        regex = stripped;
+    }
+    let mut end = regex.to_string();
+    if let Some(stripped) = wildcards.iter().find_map(|w| end.strip_suffix(w)) {
+        end = stripped.to_string();
+    } else {
+        end.push('$');
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lints(source: &str) -> Vec<(Lint, String)> {
+        lint_source(source)
+            .1
+            .into_iter()
+            .map(|(lint, d)| (lint, d.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_ambiguous_patterns() {
+        let source = r#"name: T
+prompt: P
+assertions:
+  - tool: Read
+    params:
+      file_path: "*.rs"
+  - tool: Edit
+    params:
+      file_path: "src/*.rs"
+  - tool: Bash
+    params:
+      command: "cargo (test"
+  - tool: Grep
+    params:
+      pattern: ".*\\.rs$"
+  - file_access:
+      path: ".*\\.env"
+  - stdout:
+      contains: "done.*"
+"#;
+        let found = lints(source);
+        assert_eq!(found.len(), 5);
+        assert!(found
+            .iter()
+            .all(|(lint, _)| *lint == Lint::AmbiguousPattern));
+        assert!(found[0]
+            .1
+            .starts_with("6:18: '*.rs' in 'file_path' looks like a glob"));
+        assert!(found[0].1.contains("compared literally"));
+        assert!(found[0].1.ends_with("did you mean '\\.rs$'?"));
+        assert!(found[1].1.contains("'*' repeats"));
+        assert!(found[1].1.ends_with("did you mean 'src/[^/]*\\.rs$'?"));
+        assert!(found[2].1.contains("not a valid regex"));
+        assert!(found[2].1.ends_with("did you mean 'cargo \\(test'?"));
+        assert!(found[3].1.contains("'path' is a glob"));
+        assert!(found[4].1.contains("use 'matches'"));
+    }
+
+    #[test]
+    fn test_contradictions_and_never_fails() {
+        let source = r#"name: T
+prompt: P
+assertions:
+  - tool: Write
+    called: false
+    called_after: Read
+  - tool: Bash
+    min_calls: 3
+    max_calls: 2
+  - stdout:
+      not_contains: ""
+  - any_of:
+      - tool: Read
+      - stdout:
+          matches: ".*"
+  - sequence:
+      - tool: Read
+        many: true
+  - not:
+      all_of: []
+"#;
+        let found = lints(source);
+        let codes: Vec<Lint> = found.iter().map(|(lint, _)| *lint).collect();
+        assert_eq!(
+            codes,
+            vec![
+                Lint::Contradiction,
+                Lint::Contradiction,
+                Lint::Contradiction,
+                Lint::NeverFails,
+                Lint::NeverFails,
+                Lint::Contradiction,
+            ]
+        );
+        assert!(found[0]
+            .1
+            .starts_with("4:11: 'called: false' contradicts 'called_after'"));
+        assert!(found[1]
+            .1
+            .contains("'min_calls: 3' is greater than 'max_calls: 2'"));
+        assert!(found[3].1.contains("only existence is checked"));
+        assert!(found[4].1.contains("'many: true'"));
+        assert!(found[5]
+            .1
+            .contains("'not' negates an assertion that always passes"));
+    }
+
+    #[test]
+    fn test_mixed_kinds() {
+        let source = r#"name: T
+prompt: P
+assertions:
+  - tool: Bash
+    called: false
+    stdout:
+      contains: done
+  - sequence:
+      - tool: Read
+    tool: Write
+    called: false
+  - file_access:
+      path: "**/.env"
+    params:
+      command: cat
+"#;
+        let found = lints(source);
+        assert_eq!(
+            found,
+            vec![
+                (
+                    Lint::MixedKinds,
+                    "6:5: assertion has both 'tool' and 'stdout'; split it into one assertion \
+                     per check"
+                        .to_string()
+                ),
+                (
+                    Lint::MixedKinds,
+                    "10:5: assertion has both 'sequence' and 'tool'; split it into one \
+                     assertion per check"
+                        .to_string()
+                ),
+                (
+                    Lint::MixedKinds,
+                    "14:5: 'params' only applies to 'tool' assertions, not 'file_access'"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_aliases_and_empty_tests() {
+        let source = "name: T\nprompt: P\nassertions:\n  - tool: \"read_file|Bash\"\n    \
+                      called_after: LS\n";
+        let found = lints(source);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, Lint::LegacyAlias);
+        assert_eq!(
+            found[0].1,
+            "4:11: 'read_file' in 'tool' is a legacy alias; did you mean 'Read'?"
+        );
+
+        let found = lints("name: Empty\nprompt: P\n");
+        assert_eq!(found[0].0, Lint::NoAssertions);
+        assert!(lints("name: Snap\nprompt: P\nsnapshot: true\n").is_empty());
+
+        let found = lints("name: T\nprompt: P\nassertions:\n  - tool: Raed\n");
+        assert_eq!(found[0].0, Lint::Invalid);
+        let found = lints("name: T\nprompt: P\nasserts: []\n");
+        assert_eq!(found[0].0, Lint::Invalid);
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let tmp = tempfile::tempdir().unwrap();
+        let paths: Vec<PathBuf> = ["a.yaml", "b.yaml"]
+            .iter()
+            .map(|file| {
+                let path = tmp.path().join(file);
+                let source = "name: Same\nprompt: P\nassertions:\n  - tool: Read\n";
+                std::fs::write(&path, source).unwrap();
+                path
+            })
+            .collect();
+
+        let findings = lint_files(&paths);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].lint, Lint::DuplicateName);
+        assert_eq!(findings[0].path, paths[1]);
+        assert_eq!(
+            findings[0].to_string(),
+            format!(
+                "{}:1:7: warning[duplicate-name]: test name 'Same' is also used by {}",
+                paths[1].display(),
+                paths[0].display()
+            )
+        );
+
+        let json = serde_json::to_value(&findings[0]).unwrap();
+        assert_eq!(json["lint"], "duplicate-name");
+        assert_eq!(json["severity"], "warning");
+        assert_eq!(json["line"], 1);
+    }
+
+    #[test]
+    fn test_glob_to_regex() {
+        assert_eq!(glob_to_regex("*.rs"), "\\.rs$");
+        assert_eq!(glob_to_regex("*config*"), "config");
+        assert_eq!(glob_to_regex("src/**/*.rs"), "src/(.*/)?[^/]*\\.rs$");
+        assert!(looks_like_glob("**/test"));
+        assert!(!looks_like_glob(".*\\.rs"));
+        assert!(!looks_like_glob("\\d*"));
+        assert!(!looks_like_regex("src/**/.*"));
+    }
+}
@@ -29,8 +29,23 @@
 //! let results = run_yaml_test(&test, &tool_calls);
 //! ```
 
+mod filter;
+mod lint;
 mod parser;
 mod runner;
+mod schema;
+mod template;
+mod validate;
 
-pub use parser::{load_test, parse_tool_name, Assertion, StdoutConstraints, Test, YamlError};
-pub use runner::{run_yaml_test, TestResult};
+pub use filter::TestFilter;
+pub use lint::{lint_files, Finding, Lint, Severity};
+pub use parser::{
+    load_test, parse_tool_name, parse_tool_selector, Assertion, Expectation, FileAccessConstraints,
+    SequenceEntry, SessionRequirements, Skip, SnapshotConfig, StdoutConstraints, Test, YamlError,
+};
+pub use runner::{
+    check_requirements, check_snapshot, check_trace_snapshot, run_conversation_test_in,
+    run_session_test_in, run_yaml_test, run_yaml_test_in, TestOutcome, TestResult, Turn,
+};
+pub use schema::{config_schema, test_schema};
+pub use validate::{parse_test, validate_test, Diagnostic};
@@ -3,17 +3,28 @@
 //! This module handles YAML deserialization and string-to-Tool enum conversion.
 //! All string parsing logic (case handling, aliases) lives here.
 
-use crate::fluent::Tool;
+use crate::agents::AgentOptions;
+use crate::fluent::{Tool, ToolCategory, ToolSelector};
 use anyhow::{Context, Result};
-use serde::Deserialize;
-use std::collections::HashMap;
+use schemars::JsonSchema;
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use super::validate::{parse_test, Diagnostic};
 
 /// Error type for YAML parsing issues.
 #[derive(Debug, thiserror::Error)]
 pub enum YamlError {
-    #[error("Unknown tool: '{0}'. Available tools: Read, Write, Edit, Bash, Glob, Grep, Task, WebFetch, WebSearch, NotebookEdit, AskUserQuestion, TodoWrite")]
+    #[error(
+        "Unknown tool: '{0}'. Available tools: {}. Categories: {}. Use 'mcp__<server>__<tool>' for MCP tools or 'other:<Name>' for any other tool",
+        available_tools(),
+        available_categories()
+    )]
     UnknownTool(String),
 
     #[error("IO error: {0}")]
@@ -21,26 +32,229 @@ pub enum YamlError {
 
     #[error("YAML parse error: {0}")]
     Yaml(#[from] serde_yaml::Error),
+
+    #[error("{}", format_diagnostics(path, diagnostics))]
+    Invalid {
+        path: PathBuf,
+        diagnostics: Vec<Diagnostic>,
+    },
+}
+
+/// One `path:line:column: message` line per diagnostic.
+fn format_diagnostics(path: &Path, diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(|d| d.display_in(path))
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 /// A test loaded from YAML.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Test {
     /// Human-readable name for this test.
     pub name: String,
     /// The prompt to send to the agent.
     pub prompt: String,
+    /// Follow-up user turns sent after `prompt`, each continuing the same session.
+    #[serde(default)]
+    pub conversation: Vec<String>,
     /// Agent to use for this test (defaults to "claude").
     #[serde(default)]
     pub agent: Option<String>,
+    /// Model to run the agent with (defaults to the config's `model`, then the agent's own).
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Agent CLI options (allowed tools, permission mode, ...), over the config's.
+    #[serde(default)]
+    pub agent_options: AgentOptions,
+    /// Labels for selecting tests with `--tag` and `--exclude-tag`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Skip this test, optionally with a reason: `skip: true` or `skip: "flaky on CI"`.
+    #[serde(default)]
+    pub skip: Option<Skip>,
+    /// Run only tests marked `only: true` when any test in the run is.
+    #[serde(default)]
+    pub only: bool,
+    /// `fail` marks a known gap: the test passes while it fails and fails
+    /// once it unexpectedly passes.
+    #[serde(default)]
+    pub expect: Expectation,
+    /// Issue tracking an `expect: fail` test, shown in reports.
+    #[serde(default)]
+    pub issue: Option<String>,
+    /// Values substituted for `{{name}}` in the name, prompt, params and stdout patterns.
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
+    /// Lists of values; the test runs once per combination (see [`Test::cases`]).
+    #[serde(default)]
+    pub matrix: BTreeMap<String, Vec<String>>,
+    /// The matrix values of this case, set by [`Test::cases`], as `key=value` pairs.
+    #[serde(skip)]
+    pub case: Option<String>,
+    /// Preconditions the recorded session must satisfy.
+    #[serde(default)]
+    pub requires: Option<SessionRequirements>,
+    /// Compare the trace against `__snapshots__/<file stem>.snap` next to the test.
+    #[serde(default)]
+    pub snapshot: Option<SnapshotConfig>,
     /// List of assertions to evaluate.
+    #[serde(default)]
     pub assertions: Vec<Assertion>,
 }
 
+/// Whether a test is expected to pass or to fail.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Expectation {
+    /// The test must pass (default).
+    #[default]
+    Pass,
+    /// The test documents a known failure.
+    Fail,
+}
+
+/// Skip setting: `skip: true`, or the reason as a string.
+#[derive(Debug, Clone, JsonSchema)]
+#[serde(untagged)]
+pub enum Skip {
+    /// Skip (or not) without a reason.
+    Enabled(bool),
+    /// Skip, with the reason reported instead of the results.
+    Reason(String),
+}
+
+impl Skip {
+    /// Whether the test is skipped.
+    pub fn enabled(&self) -> bool {
+        !matches!(self, Skip::Enabled(false))
+    }
+
+    /// The reason given for skipping, if any.
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            Skip::Reason(reason) => Some(reason),
+            Skip::Enabled(_) => None,
+        }
+    }
+}
+
+// Deserialized by hand rather than as an untagged enum, so a wrong value is
+// reported with its line and column instead of "did not match any variant".
+impl<'de> Deserialize<'de> for Skip {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SkipVisitor;
+
+        impl Visitor<'_> for SkipVisitor {
+            type Value = Skip;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("true, false or the reason for skipping")
+            }
+
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<Skip, E> {
+                Ok(Skip::Enabled(value))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Skip, E> {
+                Ok(Skip::Reason(value.to_string()))
+            }
+        }
+
+        deserializer.deserialize_any(SkipVisitor)
+    }
+}
+
+/// Snapshot setting: `snapshot: true`, or `snapshot: { stdout: true }` to
+/// include stdout in the stored trace.
+#[derive(Debug, Clone, Copy, JsonSchema)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum SnapshotConfig {
+    /// Enable or disable the snapshot.
+    Enabled(bool),
+    /// Enable the snapshot with options.
+    Options {
+        /// Include stdout in the snapshot.
+        #[serde(default)]
+        stdout: bool,
+    },
+}
+
+impl SnapshotConfig {
+    /// Whether the snapshot is checked.
+    pub fn enabled(&self) -> bool {
+        !matches!(self, SnapshotConfig::Enabled(false))
+    }
+
+    /// Whether stdout is part of the snapshot.
+    pub fn includes_stdout(&self) -> bool {
+        matches!(self, SnapshotConfig::Options { stdout: true })
+    }
+}
+
+// Deserialized by hand like `Skip`, so an unknown option is reported with its
+// location and the closest valid name.
+impl<'de> Deserialize<'de> for SnapshotConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Options {
+            #[serde(default)]
+            stdout: bool,
+        }
+
+        struct SnapshotVisitor;
+
+        impl<'de> Visitor<'de> for SnapshotVisitor {
+            type Value = SnapshotConfig;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("true, false or snapshot options")
+            }
+
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<SnapshotConfig, E> {
+                Ok(SnapshotConfig::Enabled(value))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<SnapshotConfig, A::Error> {
+                let options = Options::deserialize(MapAccessDeserializer::new(map))?;
+                Ok(SnapshotConfig::Options {
+                    stdout: options.stdout,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(SnapshotVisitor)
+    }
+}
+
+/// Preconditions on session metadata, as glob patterns.
+///
+/// Used to refuse sessions recorded with the wrong model or in the wrong
+/// directory before any assertion is evaluated.
+#[derive(Debug, Deserialize, Clone, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SessionRequirements {
+    /// Model that produced the session (e.g. `claude-sonnet-*`).
+    pub model: Option<String>,
+    /// Working directory the session was recorded in.
+    pub cwd: Option<String>,
+    /// Git branch checked out when the session was recorded.
+    pub git_branch: Option<String>,
+    /// Version of the agent CLI.
+    pub version: Option<String>,
+}
+
 /// A single assertion about tool usage or stdout.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(transform = super::schema::assertion_rules)]
 pub struct Assertion {
     /// Tool name (case-insensitive, supports aliases). Optional if using stdout assertion.
+    #[serde(default)]
+    #[schemars(schema_with = "super::schema::tool_name")]
     pub tool: Option<String>,
     /// Whether this tool should be called (default: true).
     #[serde(default = "default_true")]
@@ -48,8 +262,12 @@ pub struct Assertion {
     /// Parameter patterns to match (glob, regex, or exact).
     pub params: Option<HashMap<String, String>>,
     /// Assert this tool is called after another tool.
+    #[serde(default)]
+    #[schemars(schema_with = "super::schema::tool_name")]
     pub called_after: Option<String>,
     /// Assert this tool is called before another tool.
+    #[serde(default)]
+    #[schemars(schema_with = "super::schema::tool_name")]
     pub called_before: Option<String>,
     /// Assert exact number of times the tool was called.
     pub call_count: Option<u32>,
@@ -57,6 +275,14 @@ pub struct Assertion {
     pub max_calls: Option<u32>,
     /// Assert minimum number of times the tool must be called.
     pub min_calls: Option<u32>,
+    /// Only count shell commands running this program (parsed from `command`).
+    pub program: Option<String>,
+    /// Only count shell commands given all of these flags (`-rf` matches `-r -f`).
+    pub flags: Option<Vec<String>>,
+    /// Only count shell commands reading a file matching this glob.
+    pub reads_file: Option<String>,
+    /// Only count shell commands writing a file matching this glob.
+    pub writes_file: Option<String>,
     /// Assert parameters for specific call indices (1-based).
     pub nth_call_params: Option<HashMap<u32, HashMap<String, String>>>,
     /// Assert parameters for the first call.
@@ -65,10 +291,61 @@ pub struct Assertion {
     pub last_call_params: Option<HashMap<String, String>>,
     /// Stdout assertion constraints. If present, this is a stdout assertion.
     pub stdout: Option<StdoutConstraints>,
+    /// File access constraints across all tools. If present, this is a file access assertion.
+    pub file_access: Option<FileAccessConstraints>,
+    /// Ordered steps to find in the tool-call trace. If present, this is a sequence assertion.
+    pub sequence: Option<Vec<SequenceEntry>>,
+    /// Require sequence steps to match adjacent calls (default: false).
+    #[serde(default)]
+    pub strict: bool,
+    /// Passes if at least one nested assertion passes.
+    pub any_of: Option<Vec<Assertion>>,
+    /// Passes if every nested assertion passes.
+    pub all_of: Option<Vec<Assertion>>,
+    /// Passes if the nested assertion fails.
+    pub not: Option<Box<Assertion>>,
+    /// Evaluate against one turn (1 is `prompt`) instead of the whole conversation.
+    pub turn: Option<u32>,
+}
+
+/// One step of a `sequence` assertion.
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(transform = super::schema::sequence_entry_rules)]
+pub struct SequenceEntry {
+    /// Tool name, `A|B` list, or category.
+    #[schemars(schema_with = "super::schema::tool_name")]
+    pub tool: String,
+    /// `false` forbids matching calls between the surrounding steps (default: true).
+    #[serde(default = "default_true")]
+    pub called: bool,
+    /// Parameter patterns to match (glob, regex, or exact).
+    pub params: Option<HashMap<String, String>>,
+    /// Match any number of calls, including none (default: false).
+    #[serde(default)]
+    pub many: bool,
+}
+
+/// Constraints for `file_access` assertions.
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FileAccessConstraints {
+    /// Glob matched against the path, relative path or file name (default: `**`).
+    #[serde(default = "default_path_pattern")]
+    pub path: String,
+    /// Only count `read`, `write` or `execute` accesses (default: any).
+    pub mode: Option<String>,
+    /// Only count paths outside the working directory (default: false).
+    #[serde(default)]
+    pub outside_project: bool,
+    /// Whether matching accesses are allowed (default: true, which requires one).
+    #[serde(default = "default_true")]
+    pub allowed: bool,
 }
 
 /// Constraints for stdout assertions.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct StdoutConstraints {
     /// Whether stdout should exist (default: true).
     #[serde(default = "default_true")]
@@ -87,13 +364,39 @@ fn default_true() -> bool {
     true
 }
 
+fn default_path_pattern() -> String {
+    "**".to_string()
+}
+
+/// Comma-separated list of known tool names for error messages.
+fn available_tools() -> String {
+    Tool::all()
+        .iter()
+        .map(|t| t.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Comma-separated list of tool category names for error messages.
+fn available_categories() -> String {
+    ToolCategory::all()
+        .iter()
+        .map(|c| c.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// Load a test from a YAML file.
 ///
 /// # Errors
 ///
 /// Returns an error if:
 /// - The file cannot be read
-/// - The YAML is malformed
+/// - The YAML is malformed or has unknown fields
+/// - A tool name, regex, glob or field combination is invalid
+///
+/// Problems are reported as [`YamlError::Invalid`], one
+/// `path:line:column: message` line each.
 ///
 /// # Example
 ///
@@ -103,8 +406,13 @@ fn default_true() -> bool {
 /// ```
 pub fn load_test(path: &Path) -> Result<Test> {
     let content = fs::read_to_string(path).context("Failed to read test file")?;
-    let test: Test = serde_yaml::from_str(&content).context("Failed to parse YAML")?;
-    Ok(test)
+    parse_test(&content).map_err(|diagnostics| {
+        YamlError::Invalid {
+            path: path.to_path_buf(),
+            diagnostics,
+        }
+        .into()
+    })
 }
 
 /// Parse a tool name string into a Tool enum.
@@ -112,6 +420,8 @@ pub fn load_test(path: &Path) -> Result<Test> {
 /// This function handles:
 /// - Case-insensitive matching (read, READ, Read all work)
 /// - Legacy snake_case aliases (read_file -> Read)
+/// - MCP tools by full name, with optional wildcards (`mcp__github__*`)
+/// - Any other tool by exact name with the `other:` prefix (`other:MyTool`)
 ///
 /// # Errors
 ///
@@ -126,14 +436,28 @@ pub fn load_test(path: &Path) -> Result<Test> {
 /// assert_eq!(parse_tool_name("Read").unwrap(), Tool::Read);
 /// assert_eq!(parse_tool_name("read").unwrap(), Tool::Read);
 /// assert_eq!(parse_tool_name("read_file").unwrap(), Tool::Read);
+/// assert_eq!(parse_tool_name("mcp__github__*").unwrap(), Tool::mcp("github", "*"));
 /// ```
 pub fn parse_tool_name(s: &str) -> Result<Tool, YamlError> {
+    // Open-ended tools keep their exact spelling
+    if let Some(tool) = Tool::from_mcp_name(s) {
+        return Ok(tool);
+    }
+    if let Some(name) = s.strip_prefix("other:") {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(YamlError::UnknownTool(s.to_string()));
+        }
+        return Ok(Tool::Other(name.to_string()));
+    }
+
     // Case-insensitive exact matches first
     match s.to_lowercase().as_str() {
         // Primary names (match JSONL output)
         "read" => Ok(Tool::Read),
         "write" => Ok(Tool::Write),
         "edit" => Ok(Tool::Edit),
+        "multiedit" => Ok(Tool::MultiEdit),
         "bash" => Ok(Tool::Bash),
         "glob" => Ok(Tool::Glob),
         "grep" => Ok(Tool::Grep),
@@ -145,11 +469,17 @@ pub fn parse_tool_name(s: &str) -> Result<Tool, YamlError> {
         "todowrite" => Ok(Tool::TodoWrite),
         "killshell" => Ok(Tool::KillShell),
         "taskoutput" => Ok(Tool::TaskOutput),
+        "ls" => Ok(Tool::ListDirectory),
+        "skill" => Ok(Tool::Skill),
+        "slashcommand" => Ok(Tool::SlashCommand),
+        "exitplanmode" => Ok(Tool::ExitPlanMode),
+        "bashoutput" => Ok(Tool::BashOutput),
 
         // Legacy snake_case aliases (for backward compatibility)
         "read_file" => Ok(Tool::Read),
         "write_file" => Ok(Tool::Write),
         "edit_file" => Ok(Tool::Edit),
+        "multi_edit" => Ok(Tool::MultiEdit),
         "execute_command" => Ok(Tool::Bash),
         "glob_files" => Ok(Tool::Glob),
         "search_files" => Ok(Tool::Grep),
@@ -160,11 +490,50 @@ pub fn parse_tool_name(s: &str) -> Result<Tool, YamlError> {
         "todo_write" => Ok(Tool::TodoWrite),
         "kill_shell" => Ok(Tool::KillShell),
         "task_output" => Ok(Tool::TaskOutput),
+        "list_directory" => Ok(Tool::ListDirectory),
+        "slash_command" => Ok(Tool::SlashCommand),
+        "exit_plan_mode" => Ok(Tool::ExitPlanMode),
+        "bash_output" => Ok(Tool::BashOutput),
 
         _ => Err(YamlError::UnknownTool(s.to_string())),
     }
 }
 
+/// Parse the `tool:` field of an assertion.
+///
+/// Accepts a category name (`file_write`, `file_read`, `shell`, `network`),
+/// several tool names separated by `|` (`Write|Edit`), or a single tool name
+/// as understood by [`parse_tool_name`].
+///
+/// # Example
+///
+/// ```rust
+/// use aptitude::yaml::parse_tool_selector;
+/// use aptitude::{Tool, ToolCategory, ToolSelector};
+///
+/// assert_eq!(
+///     parse_tool_selector("file_write").unwrap(),
+///     ToolSelector::Category(ToolCategory::FileWrite)
+/// );
+/// assert_eq!(
+///     parse_tool_selector("Write|Edit").unwrap(),
+///     ToolSelector::AnyOf(vec![Tool::Write, Tool::Edit])
+/// );
+/// ```
+pub fn parse_tool_selector(s: &str) -> Result<ToolSelector, YamlError> {
+    if let Some(category) = ToolCategory::from_name(s) {
+        return Ok(ToolSelector::Category(category));
+    }
+    if s.contains('|') {
+        let tools = s
+            .split('|')
+            .map(|name| parse_tool_name(name.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(ToolSelector::AnyOf(tools));
+    }
+    parse_tool_name(s).map(ToolSelector::Tool)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -192,6 +561,43 @@ mod tests {
         assert_eq!(parse_tool_name("search_files").unwrap(), Tool::Grep);
     }
 
+    #[test]
+    fn test_parse_tool_name_new_tools() {
+        assert_eq!(parse_tool_name("LS").unwrap(), Tool::ListDirectory);
+        assert_eq!(parse_tool_name("Skill").unwrap(), Tool::Skill);
+        assert_eq!(parse_tool_name("slash_command").unwrap(), Tool::SlashCommand);
+        assert_eq!(parse_tool_name("ExitPlanMode").unwrap(), Tool::ExitPlanMode);
+        assert_eq!(parse_tool_name("BashOutput").unwrap(), Tool::BashOutput);
+    }
+
+    #[test]
+    fn test_parse_tool_name_open() {
+        assert_eq!(
+            parse_tool_name("mcp__github__create_issue").unwrap(),
+            Tool::mcp("github", "create_issue")
+        );
+        assert_eq!(
+            parse_tool_name("other:MyTool").unwrap(),
+            Tool::Other("MyTool".to_string())
+        );
+        assert!(parse_tool_name("other:").is_err());
+        assert!(parse_tool_name("mcp__github").is_err());
+    }
+
+    #[test]
+    fn test_parse_tool_selector() {
+        assert_eq!(
+            parse_tool_selector("network").unwrap(),
+            ToolSelector::Category(ToolCategory::Network)
+        );
+        assert_eq!(
+            parse_tool_selector("Grep | glob").unwrap(),
+            ToolSelector::AnyOf(vec![Tool::Grep, Tool::Glob])
+        );
+        assert_eq!(parse_tool_selector("Read").unwrap(), ToolSelector::Tool(Tool::Read));
+        assert!(parse_tool_selector("Read|nope").is_err());
+    }
+
     #[test]
     fn test_parse_tool_name_unknown() {
         assert!(parse_tool_name("unknown_tool").is_err());
@@ -242,6 +648,39 @@ assertions:
         assert_eq!(test.name, "Test reading files");
         assert_eq!(test.prompt, "Read the config");
         assert_eq!(test.assertions.len(), 1);
+        assert!(test.requires.is_none());
+    }
+
+    #[test]
+    fn test_deserialize_requires() {
+        let yaml = r#"
+name: "Test"
+prompt: "Prompt"
+requires:
+  model: "claude-sonnet-*"
+  git_branch: main
+assertions: []
+"#;
+        let test: Test = serde_yaml::from_str(yaml).unwrap();
+        let requires = test.requires.unwrap();
+        assert_eq!(requires.model, Some("claude-sonnet-*".to_string()));
+        assert_eq!(requires.git_branch, Some("main".to_string()));
+        assert!(requires.cwd.is_none());
+    }
+
+    #[test]
+    fn test_deserialize_snapshot() {
+        let test: Test = serde_yaml::from_str("name: T\nprompt: P\nsnapshot: true\n").unwrap();
+        let snapshot = test.snapshot.unwrap();
+        assert!(snapshot.enabled() && !snapshot.includes_stdout());
+        assert!(test.assertions.is_empty());
+
+        let yaml = "name: T\nprompt: P\nsnapshot:\n  stdout: true\n";
+        let test: Test = serde_yaml::from_str(yaml).unwrap();
+        assert!(test.snapshot.unwrap().includes_stdout());
+
+        let test: Test = serde_yaml::from_str("name: T\nprompt: P\nsnapshot: false\n").unwrap();
+        assert!(!test.snapshot.unwrap().enabled());
     }
 
     #[test]
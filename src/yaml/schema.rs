@@ -0,0 +1,164 @@
+//! JSON Schema for test files and `.aptitude.yaml`.
+//!
+//! Generated from the Rust types with `schemars`, so the schema follows the
+//! parser. Tool fields list [`Tool::all()`] and the category names for
+//! completion, and the field combinations rejected by validation are encoded
+//! as `if`/`then` rules so editors flag them while typing.
+
+use schemars::generate::SchemaSettings;
+use schemars::{json_schema, Schema, SchemaGenerator};
+use serde_json::json;
+
+use crate::config::Config;
+use crate::fluent::{Tool, ToolCategory};
+
+use super::parser::Test;
+
+/// JSON Schema (draft-07) for a YAML test file.
+///
+/// # Example
+///
+/// ```rust
+/// let schema = aptitude::yaml::test_schema();
+/// assert_eq!(schema.get("title").unwrap(), "Test");
+/// ```
+pub fn test_schema() -> Schema {
+    SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<Test>()
+}
+
+/// JSON Schema (draft-07) for the `.aptitude.yaml` discovery config.
+pub fn config_schema() -> Schema {
+    SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<Config>()
+}
+
+/// A tool name, `A|B` list or category. Known names are offered for
+/// completion; MCP and `other:` names are accepted as any string.
+pub(crate) fn tool_name(_generator: &mut SchemaGenerator) -> Schema {
+    let names: Vec<&str> = Tool::all()
+        .iter()
+        .map(|t| t.as_str())
+        .chain(ToolCategory::all().iter().map(|c| c.as_str()))
+        .collect();
+    json_schema!({
+        "type": "string",
+        "anyOf": [
+            { "enum": names },
+            { "type": "string" }
+        ]
+    })
+}
+
+/// An assertion needs exactly one kind, `tool` fields only apply to `tool`
+/// assertions, and `called: false` or `strict` exclude some other fields.
+pub(crate) fn assertion_rules(schema: &mut Schema) {
+    let kinds = [
+        "tool",
+        "sequence",
+        "stdout",
+        "file_access",
+        "any_of",
+        "all_of",
+        "not",
+    ];
+    let one_kind: Vec<_> = kinds.iter().map(|k| json!({ "required": [k] })).collect();
+    schema.insert("oneOf".to_string(), json!(one_kind));
+    // A missing `tool` is not a `null` one
+    if let Some(tool) = schema
+        .get_mut("properties")
+        .and_then(|properties| properties.get_mut("tool"))
+        .and_then(|tool| tool.as_object_mut())
+    {
+        tool.remove("default");
+    }
+    let tool_fields = [
+        "params",
+        "called_after",
+        "called_before",
+        "call_count",
+        "max_calls",
+        "min_calls",
+        "program",
+        "flags",
+        "reads_file",
+        "writes_file",
+        "nth_call_params",
+        "first_call_params",
+        "last_call_params",
+    ];
+    let any_tool_field: Vec<_> = tool_fields.iter().map(|k| json!({ "required": [k] })).collect();
+    schema.insert(
+        "allOf".to_string(),
+        json!([
+            {
+                "if": { "not": { "required": ["tool"] } },
+                "then": {
+                    "not": { "anyOf": any_tool_field },
+                    "properties": { "called": { "const": true } }
+                }
+            },
+            {
+                "if": called_false(),
+                "then": {
+                    "not": { "anyOf": [{ "required": ["call_count"] }, { "required": ["min_calls"] }] },
+                    "properties": { "max_calls": { "const": 0 } }
+                }
+            },
+            {
+                "if": { "properties": { "strict": { "const": true } }, "required": ["strict"] },
+                "then": { "required": ["sequence"] }
+            }
+        ]),
+    );
+}
+
+/// A forbidden sequence step cannot repeat.
+pub(crate) fn sequence_entry_rules(schema: &mut Schema) {
+    schema.insert("if".to_string(), called_false());
+    schema.insert(
+        "then".to_string(),
+        json!({ "properties": { "many": { "const": false } } }),
+    );
+}
+
+fn called_false() -> serde_json::Value {
+    json!({ "properties": { "called": { "const": false } }, "required": ["called"] })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_test_schema() {
+        let schema = test_schema().to_value();
+        assert_eq!(schema["$schema"], "http://json-schema.org/draft-07/schema#");
+        assert_eq!(schema["additionalProperties"], false);
+        assert!(schema["required"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("prompt")));
+
+        let assertion = &schema["definitions"]["Assertion"];
+        assert_eq!(assertion["additionalProperties"], false);
+        assert!(assertion.get("required").is_none());
+        assert!(assertion["properties"]["called"].is_object());
+        assert_eq!(assertion["oneOf"].as_array().unwrap().len(), 7);
+        assert_eq!(assertion["allOf"].as_array().unwrap().len(), 3);
+        assert!(assertion["properties"]["tool"].get("default").is_none());
+        let tools = &assertion["properties"]["tool"]["anyOf"][0]["enum"];
+        assert!(tools.as_array().unwrap().contains(&json!("Read")));
+        assert!(tools.as_array().unwrap().contains(&json!("file_write")));
+        assert!(schema["definitions"]["StdoutConstraints"].is_object());
+    }
+
+    #[test]
+    fn test_config_schema() {
+        let schema = config_schema().to_value();
+        assert!(schema["properties"]["test_pattern"].is_object());
+        assert!(schema["properties"]["exclude"].is_object());
+    }
+}
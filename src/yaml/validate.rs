@@ -0,0 +1,908 @@
+//! Load-time validation of YAML test files.
+//!
+//! Problems are reported as [`Diagnostic`]s with the line and column they
+//! were found at, so a typo like `calld: false` or `tool: Raed` fails when the
+//! file is loaded instead of silently turning the assertion into a no-op.
+
+use regex::Regex;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+use crate::fluent::{AccessMode, Tool, ToolCategory};
+
+use super::parser::{parse_tool_name, Assertion, Expectation, Test};
+use super::template::check_templates;
+
+/// A problem found in a test file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// 1-based line, if the problem could be located.
+    pub line: Option<usize>,
+    /// 1-based column, if the problem could be located.
+    pub column: Option<usize>,
+    /// What is wrong.
+    pub message: String,
+    /// Closest valid spelling, for unknown fields, tools and modes.
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(message: impl Into<String>) -> Self {
+        Self {
+            line: None,
+            column: None,
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+
+    pub(crate) fn at(mut self, location: Option<(usize, usize)>) -> Self {
+        if let Some((line, column)) = location {
+            self.line = Some(line);
+            self.column = Some(column);
+        }
+        self
+    }
+
+    pub(crate) fn suggest(mut self, suggestion: Option<&str>) -> Self {
+        self.suggestion = suggestion.map(str::to_string);
+        self
+    }
+
+    /// Format as `path:line:column: message`, as compilers do.
+    pub fn display_in(&self, path: &Path) -> String {
+        match self.line {
+            Some(_) => format!("{}:{}", path.display(), self),
+            None => format!("{}: {}", path.display(), self),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "{}:{}: ", line, column)?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "; did you mean '{}'?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse and validate a test file's contents.
+///
+/// Unknown fields and malformed YAML fail deserialization; the parsed test is
+/// then checked with [`validate_test`]. Every problem found is returned.
+///
+/// # Example
+///
+/// ```rust
+/// use aptitude::yaml::parse_test;
+///
+/// let source = "name: T\nprompt: P\nassertions:\n  - tool: Read\n    calld: false\n";
+/// let errors = parse_test(source).unwrap_err();
+/// assert_eq!(errors[0].line, Some(5));
+/// assert_eq!(errors[0].suggestion.as_deref(), Some("called"));
+/// ```
+pub fn parse_test(source: &str) -> Result<Test, Vec<Diagnostic>> {
+    let test: Test = serde_yaml::from_str(source).map_err(|e| vec![from_yaml_error(&e)])?;
+    let diagnostics = validate_test(&test, source);
+    if diagnostics.is_empty() {
+        Ok(test)
+    } else {
+        Err(diagnostics)
+    }
+}
+
+/// Check a parsed test for problems that would otherwise only surface when
+/// its assertions are evaluated.
+///
+/// Checks template variables, tool names, `stdout` regexes, the globs of
+/// `requires`, `file_access`, `reads_file` and `writes_file`, access modes and
+/// invalid field combinations; patterns are checked in every matrix case.
+/// Parameter patterns are not checked: one that is not a valid regex is
+/// compared literally, which `aptitude lint` reports.
+/// `source` is the file the test was parsed from, used to locate each
+/// problem.
+pub fn validate_test(test: &Test, source: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let document = Span::document(source);
+    let spans = assertion_spans(source, test.assertions.len());
+
+    if let Some(requires) = &test.requires {
+        let span = document.child(source, "requires").unwrap_or(document);
+        let patterns = [
+            ("model", &requires.model),
+            ("cwd", &requires.cwd),
+            ("git_branch", &requires.git_branch),
+            ("version", &requires.version),
+        ];
+        for (key, pattern) in patterns {
+            if let Some(pattern) = pattern {
+                if let Err(e) = glob::Pattern::new(pattern) {
+                    let message =
+                        format!("invalid glob '{}' in 'requires.{}': {}", pattern, key, e);
+                    let location = span.locate(source, key, pattern);
+                    diagnostics.push(Diagnostic::new(message).at(location));
+                }
+            }
+        }
+    }
+
+    if let Some(issue) = &test.issue {
+        if test.expect != Expectation::Fail {
+            let message = "'issue' only applies to 'expect: fail' tests";
+            diagnostics.push(Diagnostic::new(message).at(locate(source, "issue", issue)));
+        }
+    }
+
+    let turns = 1 + test.conversation.len();
+    for (assertion, span) in test.assertions.iter().zip(&spans) {
+        let Some(turn) = assertion.turn else { continue };
+        if turn == 0 || turn as usize > turns {
+            let message = format!("turn {} is out of range; the test has {} turn(s)", turn, turns);
+            let location = span.locate(source, "turn", &turn.to_string());
+            diagnostics.push(Diagnostic::new(message).at(location));
+        }
+    }
+
+    let template_errors = check_templates(test, source);
+    if !template_errors.is_empty() {
+        diagnostics.extend(template_errors);
+        return diagnostics;
+    }
+
+    // Check patterns after substitution, once per problem across cases
+    for case in test.cases() {
+        let mut found = Vec::new();
+        for (assertion, span) in case.assertions.iter().zip(&spans) {
+            validate_assertion_tree(assertion, source, *span, &mut found);
+        }
+        for diagnostic in found {
+            if !diagnostics.contains(&diagnostic) {
+                diagnostics.push(diagnostic);
+            }
+        }
+    }
+    diagnostics
+}
+
+/// The span of each top-level assertion, or the whole file for any that
+/// cannot be followed.
+pub(crate) fn assertion_spans(source: &str, count: usize) -> Vec<Span> {
+    let document = Span::document(source);
+    let mut spans = document.items(source, "assertions");
+    spans.resize(count, document);
+    spans
+}
+
+/// The span of each child of a combinator, in the order `any_of`, `all_of`,
+/// `not`, falling back to the parent's span.
+pub(crate) fn child_spans(assertion: &Assertion, source: &str, span: Span) -> Vec<Span> {
+    let mut spans = Vec::new();
+    for (key, children) in [("any_of", &assertion.any_of), ("all_of", &assertion.all_of)] {
+        let Some(children) = children else { continue };
+        let mut items = span.items(source, key);
+        items.resize(children.len(), span);
+        spans.extend(items);
+    }
+    if assertion.not.is_some() {
+        spans.push(span.child(source, "not").unwrap_or(span));
+    }
+    spans
+}
+
+fn validate_assertion_tree(
+    assertion: &Assertion,
+    source: &str,
+    span: Span,
+    out: &mut Vec<Diagnostic>,
+) {
+    let kinds = [
+        assertion.tool.is_some(),
+        assertion.sequence.is_some(),
+        assertion.stdout.is_some(),
+        assertion.file_access.is_some(),
+        assertion.any_of.is_some(),
+        assertion.all_of.is_some(),
+        assertion.not.is_some(),
+    ];
+    if !kinds.contains(&true) {
+        out.push(Diagnostic::new(
+            "assertion must have 'tool', 'sequence', 'stdout', 'file_access', 'any_of', \
+             'all_of' or 'not'",
+        ));
+    }
+    out.extend(check_kind(assertion, source, span));
+
+    if assertion.sequence.is_none() {
+        if let Err(message) = check_combinations(assertion) {
+            let location = assertion
+                .tool
+                .as_deref()
+                .and_then(|tool| span.locate(source, "tool", tool));
+            out.push(Diagnostic::new(message).at(location));
+        }
+    }
+
+    for (key, selector) in [
+        ("tool", &assertion.tool),
+        ("called_after", &assertion.called_after),
+        ("called_before", &assertion.called_before),
+    ] {
+        if let Some(selector) = selector {
+            check_selector(key, selector, source, span, out);
+        }
+    }
+    let steps = span.items(source, "sequence");
+    for (i, entry) in assertion.sequence.iter().flatten().enumerate() {
+        let step = steps.get(i).copied().unwrap_or(span);
+        check_selector("tool", &entry.tool, source, step, out);
+        if entry.many && !entry.called {
+            let message = format!(
+                "step {}: 'many: true' cannot be combined with 'called: false'",
+                i + 1
+            );
+            out.push(Diagnostic::new(message).at(step.locate(source, "tool", &entry.tool)));
+        }
+    }
+
+    if let Some(stdout) = &assertion.stdout {
+        for (key, pattern) in [
+            ("matches", &stdout.matches),
+            ("not_matches", &stdout.not_matches),
+        ] {
+            if let Some(pattern) = pattern {
+                if let Err(e) = Regex::new(pattern) {
+                    let message = format!("invalid regex in '{}': {}", key, regex_error(&e));
+                    out.push(Diagnostic::new(message).at(span.locate(source, key, pattern)));
+                }
+            }
+        }
+    }
+
+    let globs = [
+        ("path", assertion.file_access.as_ref().map(|c| &c.path)),
+        ("reads_file", assertion.reads_file.as_ref()),
+        ("writes_file", assertion.writes_file.as_ref()),
+    ];
+    for (key, pattern) in globs {
+        if let Some(pattern) = pattern {
+            if let Err(e) = glob::Pattern::new(pattern) {
+                let message = format!("invalid glob '{}' in '{}': {}", pattern, key, e);
+                out.push(Diagnostic::new(message).at(span.locate(source, key, pattern)));
+            }
+        }
+    }
+
+    if let Some(mode) = assertion
+        .file_access
+        .as_ref()
+        .and_then(|c| c.mode.as_deref())
+    {
+        if AccessMode::from_name(mode).is_none() {
+            let message = format!("unknown access mode '{}' (read, write or execute)", mode);
+            let suggestion = closest(mode, ["read", "write", "execute"]);
+            out.push(
+                Diagnostic::new(message)
+                    .at(span.locate(source, "mode", mode))
+                    .suggest(suggestion),
+            );
+        }
+    }
+
+    let children = assertion
+        .any_of
+        .iter()
+        .chain(&assertion.all_of)
+        .flatten()
+        .chain(assertion.not.as_deref());
+    for (child, span) in children.zip(child_spans(assertion, source, span)) {
+        if let Some(turn) = child.turn {
+            let message = "'turn' only applies to top-level assertions";
+            let location = span.locate(source, "turn", &turn.to_string());
+            out.push(Diagnostic::new(message).at(location));
+        }
+        validate_assertion_tree(child, source, span, out);
+    }
+}
+
+/// Check that an assertion has at most one kind, and no `tool` fields when
+/// it is another kind.
+///
+/// Only one kind of an assertion is evaluated, so anything else in it would
+/// be silently skipped.
+pub(crate) fn check_kind(assertion: &Assertion, source: &str, span: Span) -> Vec<Diagnostic> {
+    let mut kinds: Vec<&str> = [
+        ("tool", assertion.tool.is_some()),
+        ("sequence", assertion.sequence.is_some()),
+        ("stdout", assertion.stdout.is_some()),
+        ("file_access", assertion.file_access.is_some()),
+        ("any_of", assertion.any_of.is_some()),
+        ("all_of", assertion.all_of.is_some()),
+        ("not", assertion.not.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(key, set)| set.then_some(key))
+    .collect();
+    // In the order they are written, so the second one is pointed at
+    kinds.sort_by_key(|kind| span.locate_key(source, kind).unwrap_or((usize::MAX, 0)));
+
+    match kinds.as_slice() {
+        [] | ["tool"] => Vec::new(),
+        [kind] => tool_fields(assertion)
+            .into_iter()
+            .map(|field| {
+                let message = format!(
+                    "'{}' only applies to 'tool' assertions, not '{}'",
+                    field, kind
+                );
+                Diagnostic::new(message).at(span.locate_key(source, field))
+            })
+            .collect(),
+        [first, rest @ ..] => {
+            let message = format!(
+                "assertion has both '{}' and '{}'; split it into one assertion per check",
+                first,
+                rest.join("' and '")
+            );
+            vec![Diagnostic::new(message).at(span.locate_key(source, rest[0]))]
+        }
+    }
+}
+
+/// Fields of a `tool` assertion that are set.
+fn tool_fields(assertion: &Assertion) -> Vec<&'static str> {
+    [
+        ("called", !assertion.called),
+        ("params", assertion.params.is_some()),
+        ("called_after", assertion.called_after.is_some()),
+        ("called_before", assertion.called_before.is_some()),
+        ("call_count", assertion.call_count.is_some()),
+        ("max_calls", assertion.max_calls.is_some()),
+        ("min_calls", assertion.min_calls.is_some()),
+        ("program", assertion.program.is_some()),
+        ("flags", assertion.flags.is_some()),
+        ("reads_file", assertion.reads_file.is_some()),
+        ("writes_file", assertion.writes_file.is_some()),
+        ("nth_call_params", assertion.nth_call_params.is_some()),
+        ("first_call_params", assertion.first_call_params.is_some()),
+        ("last_call_params", assertion.last_call_params.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(key, set)| set.then_some(key))
+    .collect()
+}
+
+/// Check a tool name, `A|B` list or category, one name at a time.
+fn check_selector(key: &str, selector: &str, source: &str, span: Span, out: &mut Vec<Diagnostic>) {
+    if ToolCategory::from_name(selector).is_some() {
+        return;
+    }
+    for name in selector.split('|').map(str::trim) {
+        if let Err(e) = parse_tool_name(name) {
+            let location = span.locate(source, key, selector);
+            let diagnostic = match closest(name, known_tool_names()) {
+                Some(suggestion) => {
+                    Diagnostic::new(format!("unknown tool '{}' in '{}'", name, key))
+                        .suggest(Some(suggestion))
+                }
+                None => Diagnostic::new(e.to_string()),
+            };
+            out.push(diagnostic.at(location));
+        }
+    }
+}
+
+fn known_tool_names() -> impl Iterator<Item = &'static str> {
+    Tool::all()
+        .iter()
+        .map(|t| t.as_str())
+        .chain(ToolCategory::all().iter().map(|c| c.as_str()))
+}
+
+/// Field combinations that cannot be evaluated meaningfully.
+pub(crate) fn check_combinations(assertion: &Assertion) -> Result<(), String> {
+    if assertion.strict {
+        return Err("'strict' only applies to 'sequence' assertions".to_string());
+    }
+
+    // called: false is mutually exclusive with count assertions
+    if !assertion.called {
+        if assertion.call_count.is_some() {
+            return Err("'called: false' cannot be combined with 'call_count'".to_string());
+        }
+        if assertion.min_calls.is_some() {
+            return Err("'called: false' cannot be combined with 'min_calls'".to_string());
+        }
+        if assertion.max_calls.is_some() && assertion.max_calls != Some(0) {
+            return Err(
+                "'called: false' cannot be combined with 'max_calls' (except max_calls: 0)"
+                    .to_string(),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Turn a deserialization error into a located diagnostic, suggesting the
+/// closest field or variant name for unknown ones.
+pub(crate) fn from_yaml_error(error: &serde_yaml::Error) -> Diagnostic {
+    let text = error.to_string();
+    let message = match text.rfind(" at line ") {
+        Some(i) => &text[..i],
+        None => &text,
+    };
+    let location = error
+        .location()
+        .filter(|_| text.contains(" at line "))
+        .map(|l| (l.line(), l.column()));
+
+    let unknown = Regex::new(r"unknown (?:field|variant) `([^`]*)`, expected (.*)").unwrap();
+    if let Some(caps) = unknown.captures(message) {
+        let name = caps.get(1).map_or("", |m| m.as_str());
+        let expected = caps.get(2).map_or("", |m| m.as_str());
+        let quoted = Regex::new(r"`([^`]*)`").unwrap();
+        let candidates: Vec<&str> = quoted
+            .captures_iter(expected)
+            .filter_map(|c| c.get(1).map(|m| m.as_str()))
+            .collect();
+        if let Some(suggestion) = closest(name, candidates) {
+            let short = &message[..caps.get(2).unwrap().start() - ", expected ".len()];
+            return Diagnostic::new(short)
+                .at(location)
+                .suggest(Some(suggestion));
+        }
+    }
+    Diagnostic::new(message).at(location)
+}
+
+/// Find where `key: value` is first written in the file, as (line, column)
+/// of the value.
+pub(crate) fn locate(source: &str, key: &str, value: &str) -> Option<(usize, usize)> {
+    Span::document(source).locate(source, key, value)
+}
+
+/// The lines of the source a YAML node is written on.
+///
+/// Problems inside an assertion are searched for within its span, so a
+/// `tool: Read` in the third assertion is not reported at the first one.
+/// Only block-style YAML is followed; a node written in flow style
+/// (`{tool: Read}`) is searched as part of its parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Span {
+    /// First line (0-based).
+    start: usize,
+    /// Line after the last one.
+    end: usize,
+    /// Column (0-based) the node's keys are written at.
+    indent: usize,
+}
+
+impl Span {
+    /// The whole file.
+    pub(crate) fn document(source: &str) -> Span {
+        Span {
+            start: 0,
+            end: source.lines().count(),
+            indent: 0,
+        }
+    }
+
+    /// The items of the block sequence under `key`, in order.
+    pub(crate) fn items(&self, source: &str, key: &str) -> Vec<Span> {
+        let lines: Vec<&str> = source.lines().collect();
+        let Some(key_line) = self.key_line(&lines, key) else {
+            return Vec::new();
+        };
+
+        let mut items: Vec<Span> = Vec::new();
+        let mut dash = None;
+        let mut stop = self.end;
+        for (i, line) in lines.iter().enumerate().take(self.end).skip(key_line + 1) {
+            let Some(indent) = indentation(line) else {
+                continue;
+            };
+            let trimmed = line.trim_start();
+            let is_item = trimmed == "-" || trimmed.starts_with("- ");
+            let ended = match dash {
+                None => !is_item || indent < self.indent,
+                Some(dash) => indent < dash || (indent == dash && !is_item),
+            };
+            if ended {
+                stop = i;
+                break;
+            }
+            if is_item && dash.is_none_or(|d| d == indent) {
+                dash = Some(indent);
+                if let Some(last) = items.last_mut() {
+                    last.end = i;
+                }
+                let content = trimmed[1..].trim_start();
+                let indent = if content.is_empty() {
+                    indent + 2
+                } else {
+                    line.len() - content.len()
+                };
+                items.push(Span {
+                    start: i,
+                    end: self.end,
+                    indent,
+                });
+            }
+        }
+        if let Some(last) = items.last_mut() {
+            last.end = stop;
+        }
+        items
+    }
+
+    /// The block mapping under `key`.
+    pub(crate) fn child(&self, source: &str, key: &str) -> Option<Span> {
+        let lines: Vec<&str> = source.lines().collect();
+        let key_line = self.key_line(&lines, key)?;
+        let mut child: Option<Span> = None;
+        for (i, line) in lines.iter().enumerate().take(self.end).skip(key_line + 1) {
+            let Some(indent) = indentation(line) else {
+                continue;
+            };
+            if indent <= self.indent {
+                break;
+            }
+            match &mut child {
+                Some(child) => child.end = i + 1,
+                None => {
+                    child = Some(Span {
+                        start: i,
+                        end: i + 1,
+                        indent: key_column(line)?.0,
+                    })
+                }
+            }
+        }
+        child
+    }
+
+    /// Find where `key: value` is first written within the span, as (line,
+    /// column) of the value. Quotes and trailing comments are ignored.
+    pub(crate) fn locate(&self, source: &str, key: &str, value: &str) -> Option<(usize, usize)> {
+        let lines = source.lines().enumerate().take(self.end).skip(self.start);
+        lines.into_iter().find_map(|(i, line)| {
+            let (_, content) = key_column(line)?;
+            let raw = content.strip_prefix(key)?.strip_prefix(':')?.trim_start();
+            (scalar(raw) == value).then(|| (i + 1, line.len() - raw.len() + 1))
+        })
+    }
+
+    /// Where `key` itself is written at the span's own level, as (line, column).
+    pub(crate) fn locate_key(&self, source: &str, key: &str) -> Option<(usize, usize)> {
+        let lines: Vec<&str> = source.lines().collect();
+        self.key_line(&lines, key).map(|i| (i + 1, self.indent + 1))
+    }
+
+    /// The span's own line for `key`, not one of a nested node.
+    fn key_line(&self, lines: &[&str], key: &str) -> Option<usize> {
+        (self.start..self.end.min(lines.len())).find(|&i| {
+            key_column(lines[i]).is_some_and(|(column, content)| {
+                column == self.indent
+                    && content
+                        .strip_prefix(key)
+                        .is_some_and(|rest| rest.starts_with(':'))
+            })
+        })
+    }
+}
+
+/// Leading spaces of a line with content, or `None` for blank and comment lines.
+fn indentation(line: &str) -> Option<usize> {
+    let trimmed = line.trim_start();
+    (!trimmed.is_empty() && !trimmed.starts_with('#')).then(|| line.len() - trimmed.len())
+}
+
+/// Column and text of a line's content after any `- ` sequence markers.
+fn key_column(line: &str) -> Option<(usize, &str)> {
+    indentation(line)?;
+    let mut content = line.trim_start();
+    while let Some(rest) = content.strip_prefix("- ") {
+        content = rest.trim_start();
+    }
+    Some((line.len() - content.len(), content))
+}
+
+/// The value of a scalar as written after `key:`, without quotes or a
+/// trailing comment.
+fn scalar(raw: &str) -> String {
+    let mut chars = raw.chars();
+    match chars.next() {
+        Some('"') => {
+            let mut value = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => match chars.next() {
+                        Some('n') => value.push('\n'),
+                        Some('t') => value.push('\t'),
+                        Some(escaped) => value.push(escaped),
+                        None => {}
+                    },
+                    c => value.push(c),
+                }
+            }
+            value
+        }
+        Some('\'') => {
+            let mut value = String::new();
+            let mut rest = &raw[1..];
+            while let Some(i) = rest.find('\'') {
+                value.push_str(&rest[..i]);
+                if rest[i + 1..].starts_with('\'') {
+                    value.push('\'');
+                    rest = &rest[i + 2..];
+                } else {
+                    return value;
+                }
+            }
+            value.push_str(rest);
+            value
+        }
+        _ => {
+            let end = raw.find(" #").unwrap_or(raw.len());
+            raw[..end].trim_end().to_string()
+        }
+    }
+}
+
+/// The summary line of a regex syntax error.
+pub(crate) fn regex_error(error: &regex::Error) -> String {
+    let text = error.to_string();
+    let last = text.lines().last().unwrap_or_default().trim();
+    last.strip_prefix("error: ").unwrap_or(last).to_string()
+}
+
+/// The candidate closest to `name`, if it is a plausible typo.
+pub(crate) fn closest<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let name = name.to_lowercase();
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|c| (edit_distance(&name, &c.to_lowercase()), c))
+        .filter(|(d, _)| *d <= limit)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+/// Edit distance counting insertions, deletions, substitutions and swaps of
+/// adjacent characters (so `Raed` is one edit from `Read`).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_errors(source: &str) -> Vec<Diagnostic> {
+        parse_test(source).unwrap_err()
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let source = "name: T\nprompt: P\nassertions:\n  - tool: Read\n    param:\n      a: b\n";
+        let errors = load_errors(source);
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line, errors[0].column), (Some(5), Some(5)));
+        assert_eq!(errors[0].message, "assertions[0]: unknown field `param`");
+        assert_eq!(errors[0].suggestion.as_deref(), Some("params"));
+        assert_eq!(
+            errors[0].display_in(Path::new("t.yaml")),
+            "t.yaml:5:5: assertions[0]: unknown field `param`; did you mean 'params'?"
+        );
+
+        let errors = load_errors("name: T\nprompt: P\nxyz: 1\n");
+        assert!(errors[0].suggestion.is_none());
+        assert!(errors[0].message.contains("expected one of"));
+    }
+
+    #[test]
+    fn test_unknown_tool() {
+        let source = "name: T\nprompt: P\nassertions:\n  - tool: Read\n  - tool: \"Raed|Bash\"\n";
+        let errors = load_errors(source);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "unknown tool 'Raed' in 'tool'");
+        assert_eq!(errors[0].suggestion.as_deref(), Some("Read"));
+        assert_eq!((errors[0].line, errors[0].column), (Some(5), Some(11)));
+
+        let source = "name: T\nprompt: P\nassertions:\n  - sequence:\n      - tool: Xyzzy\n";
+        let errors = load_errors(source);
+        assert!(errors[0].message.starts_with("Unknown tool: 'Xyzzy'"));
+        assert_eq!(errors[0].line, Some(5));
+    }
+
+    #[test]
+    fn test_semantic_errors() {
+        let source = r#"name: T
+prompt: P
+assertions:
+  - tool: Bash
+    called: false
+    call_count: 2
+  - stdout:
+      matches: "(unclosed"
+  - not:
+      file_access:
+        mode: reed
+  - called: false
+  - tool: Bash
+    reads_file: "[.env"
+"#;
+        let errors = load_errors(source);
+        let messages: Vec<&str> = errors.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages.len(), 5);
+        assert_eq!(
+            messages[0],
+            "'called: false' cannot be combined with 'call_count'"
+        );
+        assert_eq!(errors[0].line, Some(4));
+        assert!(messages[1].starts_with("invalid regex in 'matches'"));
+        assert_eq!(errors[1].line, Some(8));
+        assert_eq!(errors[2].suggestion.as_deref(), Some("read"));
+        assert_eq!(errors[2].line, Some(11));
+        assert!(messages[3].starts_with("assertion must have"));
+        assert!(messages[4].starts_with("invalid glob '[.env' in 'reads_file'"));
+        assert_eq!(errors[4].line, Some(14));
+    }
+
+    #[test]
+    fn test_mixed_assertion_kinds() {
+        let source = r#"name: T
+prompt: P
+assertions:
+  - tool: Bash
+    called: false
+    stdout:
+      contains: done
+  - sequence:
+      - tool: Read
+    tool: Write
+    called: false
+  - file_access:
+      path: "**/.env"
+    params:
+      command: cat
+  - any_of:
+      - stdout:
+          contains: ok
+        tool: Read
+"#;
+        let errors = load_errors(source);
+        let messages: Vec<String> = errors.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "6:5: assertion has both 'tool' and 'stdout'; split it into one assertion \
+                 per check",
+                "10:5: assertion has both 'sequence' and 'tool'; split it into one assertion \
+                 per check",
+                "14:5: 'params' only applies to 'tool' assertions, not 'file_access'",
+                "19:9: assertion has both 'stdout' and 'tool'; split it into one assertion \
+                 per check",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_setting_errors_are_located() {
+        let errors = load_errors("name: T\nprompt: P\nsnapshot:\n  stdot: true\n");
+        assert_eq!(
+            errors[0].to_string(),
+            "4:3: snapshot: unknown field `stdot`; did you mean 'stdout'?"
+        );
+
+        let errors = load_errors("name: T\nprompt: P\nskip: 5\n");
+        assert_eq!((errors[0].line, errors[0].column), (Some(3), Some(7)));
+        assert!(errors[0]
+            .message
+            .starts_with("skip: invalid type: integer `5`"));
+
+        let source = "name: T\nprompt: P\nagent_options:\n  env_clear: \"yes\"\n";
+        let errors = load_errors(source);
+        assert_eq!(errors[0].line, Some(4));
+        assert!(errors[0]
+            .message
+            .contains("expected true, false or a list of variables"));
+
+        let source = "name: T\nprompt: P\nskip: flaky\nsnapshot: {stdout: true}\n\
+                      agent_options:\n  env_clear: [PATH]\n";
+        let test = parse_test(source).unwrap();
+        assert_eq!(test.skip.unwrap().reason(), Some("flaky"));
+        assert!(test.snapshot.unwrap().includes_stdout());
+    }
+
+    #[test]
+    fn test_locations_within_assertion() {
+        let source = r#"name: T
+prompt: P
+assertions:
+  - tool: Read  # the first read
+  - tool: Read
+    called: false
+    min_calls: 1
+  - any_of:
+      - tool: Bash
+      - not:
+          tool: "Raed"
+  - sequence:
+      - tool: Read
+      - tool: Raed
+"#;
+        let errors = load_errors(source);
+        let locations: Vec<String> = errors.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            locations,
+            vec![
+                "5:11: 'called: false' cannot be combined with 'min_calls'",
+                "11:17: unknown tool 'Raed' in 'tool'; did you mean 'Read'?",
+                "14:15: unknown tool 'Raed' in 'tool'; did you mean 'Read'?",
+            ]
+        );
+        assert_eq!(locate(source, "tool", "Read"), Some((4, 11)));
+    }
+
+    #[test]
+    fn test_issue_requires_expect_fail() {
+        let errors = load_errors("name: T\nprompt: P\nissue: \"#42\"\n");
+        assert_eq!(errors[0].message, "'issue' only applies to 'expect: fail' tests");
+        assert_eq!(errors[0].line, Some(3));
+        assert!(parse_test("name: T\nprompt: P\nexpect: fail\nissue: \"#42\"\n").is_ok());
+    }
+
+    #[test]
+    fn test_turn_range() {
+        let source = "name: T\nprompt: P\nconversation: [\"No really\"]\nassertions:\n  \
+                      - tool: Bash\n    turn: 3\n";
+        let errors = load_errors(source);
+        assert_eq!(errors[0].to_string(), "6:11: turn 3 is out of range; the test has 2 turn(s)");
+
+        let source = "name: T\nprompt: P\nassertions:\n  - not:\n      tool: Bash\n      turn: 1\n";
+        let errors = load_errors(source);
+        assert_eq!(errors[0].message, "'turn' only applies to top-level assertions");
+
+        let source = "name: T\nprompt: P\nconversation: [Again]\nassertions:\n  \
+                      - tool: Bash\n    turn: 2\n";
+        assert!(parse_test(source).is_ok());
+    }
+
+    #[test]
+    fn test_closest() {
+        assert_eq!(closest("calld", ["called", "tool"]), Some("called"));
+        assert_eq!(closest("bash", ["Bash"]), Some("Bash"));
+        assert_eq!(closest("Raed", ["Read", "Write"]), Some("Read"));
+        assert_eq!(closest("zzz", ["called", "tool"]), None);
+    }
+}